@@ -0,0 +1,19 @@
+#!/bin/sh
+# Builds wslplugins-agent as a static Linux binary and checks that it does not need a loader.
+# Usage: ./build-agent.sh [target], x86_64-unknown-linux-musl by default
+set -eu
+
+target=${1:-x86_64-unknown-linux-musl}
+cd "$(dirname "$0")"
+
+if command -v rustup >/dev/null 2>&1; then
+    rustup target add "$target"
+fi
+cargo build -p wslplugins-agent --release --target "$target"
+
+agent=target/$target/release/wslplugins-agent
+if readelf -l "$agent" | grep -q INTERP; then
+    echo "$agent is dynamically linked" >&2
+    exit 1
+fi
+echo "$agent"
@@ -3,8 +3,8 @@ use etc_os_release::OsRelease;
 use fern::{log_file, Dispatch};
 use log::{info, warn, LevelFilter};
 use log_instrument::instrument;
-use std::{env, io::Read};
-use windows::{
+use std::{env, io::Read, time::Duration};
+use wslplugins_rs::windows::{
     core::{Error, Result, GUID},
     Win32::Foundation::E_FAIL,
 };
@@ -60,7 +60,7 @@ impl<'a> WSLPluginV1<'a> for Plugin<'a> {
         );
 
         let ver_args = ["/bin/cat", "/proc/version"];
-        match self.api.execute_binary(session, &ver_args[0], &ver_args) {
+        match self.api.execute_binary(session, ver_args[0], &ver_args) {
             Ok(mut stream) => {
                 let mut buf = String::new();
                 if stream.read_to_string(&mut buf).is_ok_and(|size| size != 0) {
@@ -78,7 +78,7 @@ impl<'a> WSLPluginV1<'a> for Plugin<'a> {
             }
         };
         let ver_args = ["/bin/cat", "/proc/version"];
-        match self.api.execute_binary(session, &ver_args[0], &ver_args) {
+        match self.api.execute_binary(session, ver_args[0], &ver_args) {
             Ok(mut stream) => {
                 let mut buf = String::new();
                 if stream.read_to_string(&mut buf).is_ok_and(|size| size != 0) {
@@ -145,25 +145,32 @@ impl<'a> WSLPluginV1<'a> for Plugin<'a> {
 
 impl Plugin<'_> {
     fn log_os_release(&self, session: &WSLSessionInformation, distro_id: Option<&GUID>) {
-        let args: [&str; 2] = ["/bin/cat", "/etc/os-release"];
-        let tcp_stream = match distro_id {
-            Some(dist_id) => self
-                .api
-                .execute_binary_in_distribution(session, dist_id, &args[0], &args),
-            None => self.api.execute_binary(session, &args[0], &args),
-        };
-        let result = tcp_stream;
-        match result {
-            Ok(stream) => match OsRelease::from_reader(stream) {
-                Ok(release) => {
-                    if let Some(version) = release.version() {
-                        info!("{}: ({})", release.name(), version)
-                    } else {
-                        info!("{}", release.name())
+        let mut command = self.api.command(session, "/bin/cat");
+        command.arg("/etc/os-release");
+        if let Some(dist_id) = distro_id {
+            command.in_distribution(dist_id);
+        }
+        let options = ExecOptions::default()
+            .with_timeout(Duration::from_secs(5))
+            .with_max_output_bytes(64 * 1024);
+        match command.output_with_options(&options) {
+            Ok(output) if output.status.success() => {
+                match OsRelease::from_reader(&output.stdout[..]) {
+                    Ok(release) => {
+                        if let Some(version) = release.version() {
+                            info!("{}: ({})", release.name(), version)
+                        } else {
+                            info!("{}", release.name())
+                        }
                     }
+                    Err(err) => warn!("{}", err),
                 }
-                Err(err) => warn!("{}", err),
-            },
+            }
+            Ok(output) => warn!(
+                "Unable to read /etc/os-release ({}): {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
             Err(err) => {
                 warn!("Error on binary execution: {}", err)
             }
@@ -0,0 +1,516 @@
+//! Agent launched by a plugin in a distribution, answering the JSON-RPC requests of
+//! [`AgentClient`](wslplugins_rs::agent::AgentClient) on its stdin and stdout.
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use wslplugins_rs::agent::{
+    error_code, Bytes, CommandOutput, Method, ProcessInfo, Request, Response, RpcError, RunCommand,
+    DEFAULT_COMMAND_TIMEOUT, JSONRPC_VERSION,
+};
+use wslplugins_rs::framing::{read_frame, write_frame};
+
+const METHODS: [&str; 4] = ["read_file", "list_processes", "os_release", "run_command"];
+
+fn rpc_error(code: i64, message: String) -> RpcError {
+    RpcError { code, message }
+}
+
+fn io_error(path: &Path, err: io::Error) -> RpcError {
+    rpc_error(error_code::IO_ERROR, format!("{}: {}", path.display(), err))
+}
+
+pub struct Agent {
+    root: PathBuf,
+}
+
+impl Default for Agent {
+    fn default() -> Self {
+        Self::with_root("/")
+    }
+}
+
+impl Agent {
+    /// Agent reading the files, `/proc` included, below `root` instead of `/`.
+    pub fn with_root<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Answers the requests received on `stream` until the plugin shuts down its side.
+    pub fn serve<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+        while let Some(frame) = read_frame(&mut stream)? {
+            let response = match serde_json::from_slice(&frame) {
+                Ok(request) => self.handle(request),
+                Err(err) => {
+                    Response::error(None, rpc_error(error_code::PARSE_ERROR, err.to_string()))
+                }
+            };
+            write_frame(&mut stream, &serde_json::to_vec(&response)?)?;
+        }
+        Ok(())
+    }
+
+    pub fn handle(&self, request: Value) -> Response {
+        let id = request.get("id").and_then(Value::as_u64);
+        let request = match serde_json::from_value::<Request>(request.clone()) {
+            Ok(request) if request.jsonrpc == JSONRPC_VERSION => request,
+            Ok(request) => {
+                return Response::error(
+                    id,
+                    rpc_error(
+                        error_code::INVALID_REQUEST,
+                        format!("unsupported JSON-RPC version {:?}", request.jsonrpc),
+                    ),
+                )
+            }
+            Err(err) => return Response::error(id, Self::invalid(&request, err)),
+        };
+        let result = match request.method {
+            Method::ReadFile { path } => self
+                .read_file(&path)
+                .map(|data| serde_json::json!(Bytes(data))),
+            Method::ListProcesses => self
+                .list_processes()
+                .map(|processes| serde_json::json!(processes)),
+            Method::OsRelease => self.os_release().map(|fields| serde_json::json!(fields)),
+            Method::RunCommand(command) => {
+                run_command(&command).map(|output| serde_json::json!(output))
+            }
+        };
+        match result {
+            Ok(result) => Response::result(request.id, result),
+            Err(err) => Response::error(Some(request.id), err),
+        }
+    }
+
+    // Tells an unknown method from invalid parameters
+    fn invalid(request: &Value, err: serde_json::Error) -> RpcError {
+        match request.get("method").and_then(Value::as_str) {
+            Some(method) if METHODS.contains(&method) => {
+                rpc_error(error_code::INVALID_PARAMS, err.to_string())
+            }
+            Some(method) => rpc_error(
+                error_code::METHOD_NOT_FOUND,
+                format!("unknown method {:?}", method),
+            ),
+            None => rpc_error(error_code::INVALID_REQUEST, err.to_string()),
+        }
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, RpcError> {
+        match path.strip_prefix('/') {
+            Some(relative) => Ok(self.root.join(relative)),
+            None => Err(rpc_error(
+                error_code::INVALID_PARAMS,
+                format!("{:?} is not an absolute path", path),
+            )),
+        }
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, RpcError> {
+        let path = self.resolve(path)?;
+        fs::read(&path).map_err(|err| io_error(&path, err))
+    }
+
+    fn list_processes(&self) -> Result<Vec<ProcessInfo>, RpcError> {
+        let proc = self.resolve("/proc")?;
+        let entries = fs::read_dir(&proc).map_err(|err| io_error(&proc, err))?;
+        let mut processes: Vec<ProcessInfo> = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let pid = entry.file_name().to_str()?.parse().ok()?;
+                // The process may have exited since the directory was listed
+                read_process(&entry.path(), pid)
+            })
+            .collect();
+        processes.sort_by_key(|process| process.pid);
+        Ok(processes)
+    }
+
+    fn os_release(&self) -> Result<BTreeMap<String, String>, RpcError> {
+        let etc = self.resolve("/etc/os-release")?;
+        let content = fs::read_to_string(&etc).or_else(|_| {
+            let usr = self.resolve("/usr/lib/os-release")?;
+            fs::read_to_string(&usr).map_err(|err| io_error(&usr, err))
+        })?;
+        Ok(parse_os_release(&content))
+    }
+}
+
+fn read_process(dir: &Path, pid: u32) -> Option<ProcessInfo> {
+    let stat = fs::read_to_string(dir.join("stat")).ok()?;
+    // `pid (name) state ppid ...`, the name may contain spaces and parentheses
+    let (head, tail) = stat.rsplit_once(')')?;
+    let (_, name) = head.split_once('(')?;
+    let ppid = tail.split_whitespace().nth(1)?.parse().ok()?;
+    let cmdline = fs::read(dir.join("cmdline")).unwrap_or_default();
+    Some(ProcessInfo {
+        pid,
+        ppid,
+        name: name.to_owned(),
+        cmdline: cmdline
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect(),
+    })
+}
+
+fn parse_os_release(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let unquoted = ['"', '\'']
+                .iter()
+                .find_map(|&quote| value.strip_prefix(quote)?.strip_suffix(quote))
+                .unwrap_or(value);
+            (key.trim().to_owned(), unquoted.to_owned())
+        })
+        .collect()
+}
+
+fn run_command(command: &RunCommand) -> Result<CommandOutput, RpcError> {
+    let mut process = Command::new(&command.program);
+    process
+        .args(&command.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(dir) = &command.current_dir {
+        process.current_dir(dir);
+    }
+    // In a group of its own, so that the processes it started are killed with it
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    let program_error = |err| io_error(Path::new(&command.program), err);
+    let mut child = process.spawn().map_err(program_error)?;
+    let stdout = read_to_end(child.stdout.take());
+    let stderr = read_to_end(child.stderr.take());
+    let timeout = command
+        .timeout_ms
+        .map_or(DEFAULT_COMMAND_TIMEOUT, Duration::from_millis);
+    let status = match wait_timeout(&mut child, timeout) {
+        Ok(Some(status)) => status,
+        result => {
+            kill(&mut child);
+            return Err(match result {
+                Err(err) => program_error(err),
+                _ => rpc_error(
+                    error_code::TIMEOUT,
+                    format!("{} did not exit within {:?}", command.program, timeout),
+                ),
+            });
+        }
+    };
+    // Whatever it left running in the background would keep the pipes open
+    kill_group(&child);
+    Ok(CommandOutput {
+        status: status.code(),
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_to_end<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut data = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut data);
+        }
+        data
+    })
+}
+
+fn wait_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        thread::sleep((deadline - now).min(Duration::from_millis(10)));
+    }
+}
+
+// The group id stays reserved while a process of the group is alive, even once its leader is
+// reaped, so this cannot reach another group
+fn kill_group(child: &Child) {
+    #[cfg(unix)]
+    // SAFETY: only sends a signal
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = child;
+}
+
+fn kill(child: &mut Child) {
+    kill_group(child);
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::os::unix::net::UnixStream;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use wslplugins_rs::agent::{AgentClient, AgentError, Outcome};
+
+    // Fake root of a distribution, removed on drop
+    struct Root(PathBuf);
+
+    impl Root {
+        fn new() -> Self {
+            static COUNT: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "wslplugins-agent-{}-{}",
+                std::process::id(),
+                COUNT.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, path: &str, content: &[u8]) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    impl Drop for Root {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn client(root: &Root) -> (AgentClient<UnixStream>, thread::JoinHandle<io::Result<()>>) {
+        let (plugin, agent) = UnixStream::pair().unwrap();
+        let agent_root = root.0.clone();
+        let agent = thread::spawn(move || Agent::with_root(agent_root).serve(agent));
+        (AgentClient::new(plugin), agent)
+    }
+
+    #[test]
+    fn test_read_file() {
+        let root = Root::new();
+        root.write("etc/hostname", b"host\n");
+        let (mut client, agent) = client(&root);
+        assert_eq!(client.read_file("/etc/hostname").unwrap(), b"host\n");
+        let err = client.read_file("/etc/missing").unwrap_err();
+        assert!(matches!(
+            err,
+            AgentError::Rpc(RpcError {
+                code: error_code::IO_ERROR,
+                ..
+            })
+        ));
+        let err = client.read_file("etc/hostname").unwrap_err();
+        assert!(matches!(
+            err,
+            AgentError::Rpc(RpcError {
+                code: error_code::INVALID_PARAMS,
+                ..
+            })
+        ));
+        drop(client);
+        agent.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_list_processes() {
+        let root = Root::new();
+        root.write("proc/1/stat", b"1 (init) S 0 1 1 0 -1");
+        root.write("proc/1/cmdline", b"/init\0");
+        root.write("proc/42/stat", b"42 (my (odd) name) S 1 42 42 0 -1");
+        root.write("proc/42/cmdline", b"/usr/bin/odd\0--flag\0");
+        root.write("proc/7/stat", b"7 (kworker/0:0) I 2 0 0 0 -1");
+        root.write("proc/self/stat", b"");
+        root.write("proc/uptime", b"1.0 1.0");
+        let (mut client, _agent) = client(&root);
+        assert_eq!(
+            client.list_processes().unwrap(),
+            [
+                ProcessInfo {
+                    pid: 1,
+                    ppid: 0,
+                    name: "init".into(),
+                    cmdline: vec!["/init".into()],
+                },
+                ProcessInfo {
+                    pid: 7,
+                    ppid: 2,
+                    name: "kworker/0:0".into(),
+                    cmdline: vec![],
+                },
+                ProcessInfo {
+                    pid: 42,
+                    ppid: 1,
+                    name: "my (odd) name".into(),
+                    cmdline: vec!["/usr/bin/odd".into(), "--flag".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_os_release() {
+        let root = Root::new();
+        root.write(
+            "usr/lib/os-release",
+            b"# Debian\nPRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nID=debian\nVERSION_ID='12'\n\n",
+        );
+        let (mut client, _agent) = client(&root);
+        let fields = client.os_release().unwrap();
+        assert_eq!(fields["PRETTY_NAME"], "Debian GNU/Linux 12 (bookworm)");
+        assert_eq!(fields["ID"], "debian");
+        assert_eq!(fields["VERSION_ID"], "12");
+        assert_eq!(fields.len(), 3);
+        root.write("etc/os-release", b"ID=ubuntu\n");
+        assert_eq!(client.os_release().unwrap()["ID"], "ubuntu");
+    }
+
+    #[test]
+    fn test_run_command() {
+        let root = Root::new();
+        let (mut client, _agent) = client(&root);
+        let output = client
+            .run_command(RunCommand {
+                program: "/bin/sh".into(),
+                args: vec!["-c".into(), "pwd; echo oops >&2; exit 3".into()],
+                current_dir: Some("/".into()),
+                timeout_ms: None,
+            })
+            .unwrap();
+        assert_eq!(output.status, Some(3));
+        assert_eq!(output.stdout, b"/\n");
+        assert_eq!(output.stderr, b"oops\n");
+        let err = client
+            .run_command(RunCommand {
+                program: "/nonexistent".into(),
+                args: vec![],
+                current_dir: None,
+                timeout_ms: None,
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AgentError::Rpc(RpcError {
+                code: error_code::IO_ERROR,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_run_command_timeout() {
+        let root = Root::new();
+        let (mut client, _agent) = client(&root);
+        let late = root.0.join("late");
+        let start = std::time::Instant::now();
+        let err = client
+            .run_command(RunCommand {
+                program: "/bin/sh".into(),
+                args: vec![
+                    "-c".into(),
+                    format!("(sleep 1; touch {}) & sleep 30", late.display()),
+                ],
+                current_dir: None,
+                timeout_ms: Some(200),
+            })
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AgentError::Rpc(RpcError {
+                code: error_code::TIMEOUT,
+                ..
+            })
+        ));
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        // The background process went down with the shell
+        thread::sleep(std::time::Duration::from_millis(1500));
+        assert!(!late.exists());
+    }
+
+    #[test]
+    fn test_run_command_background_process() {
+        let root = Root::new();
+        let (mut client, _agent) = client(&root);
+        let start = std::time::Instant::now();
+        let output = client
+            .run_command(RunCommand {
+                program: "/bin/sh".into(),
+                args: vec!["-c".into(), "echo started; sleep 600 &".into()],
+                current_dir: None,
+                timeout_ms: None,
+            })
+            .unwrap();
+        assert_eq!(output.status, Some(0));
+        assert_eq!(output.stdout, b"started\n");
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_read_file_as_base64() {
+        let root = Root::new();
+        root.write("bin/blob", &[0, 255, 10]);
+        let response = Agent::with_root(&root.0)
+            .handle(json!({"jsonrpc": "2.0", "id": 1, "method": "read_file", "params": {"path": "/bin/blob"}}));
+        assert_eq!(response.outcome, Outcome::Result(json!("AP8K")));
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        let agent = Agent::default();
+        let code = |request: Value| match agent.handle(request).outcome {
+            Outcome::Error(err) => err.code,
+            Outcome::Result(result) => panic!("unexpected result {}", result),
+        };
+        assert_eq!(
+            code(json!({"jsonrpc": "2.0", "id": 1, "method": "reboot"})),
+            error_code::METHOD_NOT_FOUND
+        );
+        assert_eq!(
+            code(json!({"jsonrpc": "2.0", "id": 1, "method": "read_file", "params": {}})),
+            error_code::INVALID_PARAMS
+        );
+        assert_eq!(
+            code(json!({"jsonrpc": "1.0", "id": 1, "method": "os_release"})),
+            error_code::INVALID_REQUEST
+        );
+        assert_eq!(code(json!([1, 2])), error_code::INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let (mut plugin, agent) = UnixStream::pair().unwrap();
+        let agent = thread::spawn(move || Agent::default().serve(agent));
+        write_frame(&mut plugin, b"{not json").unwrap();
+        let response: Response =
+            serde_json::from_slice(&read_frame(&mut plugin).unwrap().unwrap()).unwrap();
+        assert_eq!(response.id, None);
+        assert!(matches!(
+            response.outcome,
+            Outcome::Error(RpcError {
+                code: error_code::PARSE_ERROR,
+                ..
+            })
+        ));
+        drop(plugin);
+        agent.join().unwrap().unwrap();
+    }
+}
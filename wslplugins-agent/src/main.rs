@@ -0,0 +1,14 @@
+use std::process::ExitCode;
+use wslplugins_agent::Agent;
+use wslplugins_rs::framing::Stdio;
+
+fn main() -> ExitCode {
+    // stdin and stdout are the socket of the plugin, stderr is the only place left to report
+    match Agent::default().serve(Stdio::new()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("wslplugins-agent: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
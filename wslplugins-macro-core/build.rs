@@ -1,5 +1,4 @@
 use std::{env, fs::File, io::Write, path::PathBuf};
-use struct_field_names_as_array;
 use struct_field_names_as_array::FieldNamesAsSlice;
 use wslplugins_sys::WSLPluginHooksV1;
 
@@ -19,6 +18,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         file,
         "#[derive(EnumIter, EnumString, Clone, Copy, Display, Debug, PartialEq, Eq, Hash)]"
     )?;
+    writeln!(file, "#[allow(clippy::enum_variant_names)]")?;
     writeln!(file, "pub enum Hooks {{")?;
 
     // Générer chaque variante de l'enum
@@ -28,5 +28,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Fin de l'enum
     writeln!(file, "}}")?;
+
+    // The hooks follow the header of wslplugins-sys, whose features may be unified with another
+    // dependency, so the code handling them is gated on what was generated, not on our features
+    println!("cargo:rustc-check-cfg=cfg(distribution_registration_hooks)");
+    if items.contains(&"OnDistributionRegistered") {
+        println!("cargo:rustc-cfg=distribution_registration_hooks");
+    }
     Ok(())
 }
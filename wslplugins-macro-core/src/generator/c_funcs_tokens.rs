@@ -3,110 +3,181 @@ use quote::quote;
 use syn::{parse_str, Ident, Result};
 
 use crate::hooks::Hooks;
+use crate::parser::{PanicStrategy, PluginArgs};
 
-pub(super) fn get_c_func_tokens(hook: Hooks) -> Result<Option<TokenStream>> {
+pub(super) fn get_c_func_tokens(hook: Hooks, args: &PluginArgs) -> Result<Option<TokenStream>> {
+    let krate = &args.krate;
     let c_method_ident: Ident = parse_str(hook.get_c_method_name().as_str())?;
     let trait_method_ident: Ident = parse_str(&hook.get_trait_method_name())?;
+    let name = hook.get_trait_method_name();
+    let guarded = guard_tokens(&name, plugin_error_allowed(hook), args);
 
     let ok_result = match hook {
         Hooks::OnVMStarted => Some(quote! {
             extern "C" fn #c_method_ident(
-                session: *const ::wslplugins_rs::sys::WSLSessionInformation,
-                settings: *const ::wslplugins_rs::sys::WSLVmCreationSettings,
-            ) -> ::windows::core::HRESULT {
-                let session_ptr = unsafe { &*session };
-                let settings_ptr = unsafe { &*settings };
-                if let Some(plugin) = PLUGIN.get() {
-                    plugin.#trait_method_ident(
-                        &::wslplugins_rs::WSLSessionInformation::from(session_ptr),
-                        &::wslplugins_rs::WSLVmCreationSettings::from(settings_ptr),
-                    ).into()
-                } else {
-                    ::windows::Win32::Foundation::E_FAIL
-                }
+                session: *const #krate::sys::WSLSessionInformation,
+                settings: *const #krate::sys::WSLVmCreationSettings,
+            ) -> #krate::windows::core::HRESULT {
+                let body = || {
+                    let Some(session_ptr) = (unsafe { session.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "session");
+                    };
+                    let Some(settings_ptr) = (unsafe { settings.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "settings");
+                    };
+                    if let Some(plugin) = PLUGIN.get() {
+                        plugin.#trait_method_ident(
+                            &#krate::WSLSessionInformation::from(session_ptr),
+                            &#krate::WSLVmCreationSettings::from(settings_ptr),
+                        ).into()
+                    } else {
+                        #krate::plugin_not_initialized(#name)
+                    }
+                };
+                #guarded
             }
         }),
         Hooks::OnVMStopping => Some(quote! {
             extern "C" fn #c_method_ident(
-                session: *const ::wslplugins_rs::sys::WSLSessionInformation
-            ) -> ::windows::core::HRESULT {
-                let session_ptr = unsafe { &*session };
-                if let Some(plugin) = PLUGIN.get() {
-                    plugin.#trait_method_ident(&::wslplugins_rs::WSLSessionInformation::from(session_ptr)).into()
-                } else {
-                    ::windows::Win32::Foundation::E_FAIL
-                }
+                session: *const #krate::sys::WSLSessionInformation
+            ) -> #krate::windows::core::HRESULT {
+                let body = || {
+                    let Some(session_ptr) = (unsafe { session.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "session");
+                    };
+                    if let Some(plugin) = PLUGIN.get() {
+                        plugin.#trait_method_ident(&#krate::WSLSessionInformation::from(session_ptr)).into()
+                    } else {
+                        #krate::plugin_not_initialized(#name)
+                    }
+                };
+                #guarded
             }
         }),
         Hooks::OnDistributionStarted => Some(quote! {
             extern "C" fn #c_method_ident(
-                session: *const ::wslplugins_rs::sys::WSLSessionInformation,
-                distribution: *const ::wslplugins_rs::sys::WSLDistributionInformation,
-            ) -> ::windows::core::HRESULT {
-                let session_ptr = unsafe { &*session };
-                let distribution_ptr = unsafe { &*distribution };
-                if let Some(plugin) = PLUGIN.get() {
-                    plugin.#trait_method_ident(
-                        &::wslplugins_rs::WSLSessionInformation::from(session_ptr),
-                        &::wslplugins_rs::DistributionInformation::from(distribution_ptr),
-                    ).into()
-                } else {
-                    ::windows::Win32::Foundation::E_FAIL
-                }
+                session: *const #krate::sys::WSLSessionInformation,
+                distribution: *const #krate::sys::WSLDistributionInformation,
+            ) -> #krate::windows::core::HRESULT {
+                let body = || {
+                    let Some(session_ptr) = (unsafe { session.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "session");
+                    };
+                    let Some(distribution_ptr) = (unsafe { distribution.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "distribution");
+                    };
+                    if let Some(plugin) = PLUGIN.get() {
+                        plugin.#trait_method_ident(
+                            &#krate::WSLSessionInformation::from(session_ptr),
+                            &#krate::DistributionInformation::from(distribution_ptr),
+                        ).into()
+                    } else {
+                        #krate::plugin_not_initialized(#name)
+                    }
+                };
+                #guarded
             }
         }),
         Hooks::OnDistributionStopping => Some(quote! {
             extern "C" fn #c_method_ident(
-                session: *const ::wslplugins_rs::sys::WSLSessionInformation,
-                distribution: *const ::wslplugins_rs::sys::WSLDistributionInformation,
-            ) -> ::windows::core::HRESULT {
-                let session_ptr = unsafe { &*session };
-                let distribution_ptr = unsafe { &*distribution };
-                if let Some(plugin) = PLUGIN.get() {
-                    plugin.#trait_method_ident(
-                        &::wslplugins_rs::WSLSessionInformation::from(session_ptr),
-                        &::wslplugins_rs::DistributionInformation::from(distribution_ptr),
-                    ).into()
-                } else {
-                    ::windows::Win32::Foundation::E_FAIL
-                }
+                session: *const #krate::sys::WSLSessionInformation,
+                distribution: *const #krate::sys::WSLDistributionInformation,
+            ) -> #krate::windows::core::HRESULT {
+                let body = || {
+                    let Some(session_ptr) = (unsafe { session.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "session");
+                    };
+                    let Some(distribution_ptr) = (unsafe { distribution.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "distribution");
+                    };
+                    if let Some(plugin) = PLUGIN.get() {
+                        plugin.#trait_method_ident(
+                            &#krate::WSLSessionInformation::from(session_ptr),
+                            &#krate::DistributionInformation::from(distribution_ptr),
+                        ).into()
+                    } else {
+                        #krate::plugin_not_initialized(#name)
+                    }
+                };
+                #guarded
             }
         }),
+        #[cfg(distribution_registration_hooks)]
         Hooks::OnDistributionRegistered => Some(quote! {
             extern "C" fn #c_method_ident(
-                session: *const ::wslplugins_rs::sys::WSLSessionInformation,
-                distribution:  *const ::wslplugins_rs::sys::WSLOfflineDistributionInformation,
-            ) -> ::windows::core::HRESULT {
-                let session_ptr = unsafe { &*session };
-                let distribution_ptr = unsafe { &*distribution };
-                if let Some(plugin) = PLUGIN.get() {
-                    plugin.#trait_method_ident(
-                        &::wslplugins_rs::WSLSessionInformation::from(session_ptr),
-                        &::wslplugins_rs::OfflineDistributionInformation::from(distribution_ptr),
-                    ).into()
-                } else {
-                    ::windows::Win32::Foundation::E_FAIL
-                }
+                session: *const #krate::sys::WSLSessionInformation,
+                distribution:  *const #krate::sys::WslOfflineDistributionInformation,
+            ) -> #krate::windows::core::HRESULT {
+                let body = || {
+                    let Some(session_ptr) = (unsafe { session.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "session");
+                    };
+                    let Some(distribution_ptr) = (unsafe { distribution.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "distribution");
+                    };
+                    if let Some(plugin) = PLUGIN.get() {
+                        plugin.#trait_method_ident(
+                            &#krate::WSLSessionInformation::from(session_ptr),
+                            &#krate::OfflineDistributionInformation::from(distribution_ptr),
+                        ).into()
+                    } else {
+                        #krate::plugin_not_initialized(#name)
+                    }
+                };
+                #guarded
             }
         }),
+        #[cfg(distribution_registration_hooks)]
         Hooks::OnDistributionUnregistered => Some(quote! {
             extern "C" fn #c_method_ident(
-                session: *const ::wslplugins_rs::sys::WSLSessionInformation,
-                distribution:  *const ::wslplugins_rs::sys::WSLOfflineDistributionInformation,
-            ) -> ::windows::core::HRESULT {
-                let session_ptr = unsafe { &*session };
-                let distribution_ptr = unsafe { &*distribution };
-                if let Some(plugin) = PLUGIN.get() {
-                    plugin.#trait_method_ident(
-                        &::wslplugins_rs::WSLSessionInformation::from(session_ptr),
-                        &::wslplugins_rs::OfflineDistributionInformation::from(distribution_ptr),
-                    ).into()
-                } else {
-                    ::windows::Win32::Foundation::E_FAIL
-                }
+                session: *const #krate::sys::WSLSessionInformation,
+                distribution:  *const #krate::sys::WslOfflineDistributionInformation,
+            ) -> #krate::windows::core::HRESULT {
+                let body = || {
+                    let Some(session_ptr) = (unsafe { session.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "session");
+                    };
+                    let Some(distribution_ptr) = (unsafe { distribution.as_ref() }) else {
+                        return #krate::null_hook_argument(#name, "distribution");
+                    };
+                    if let Some(plugin) = PLUGIN.get() {
+                        plugin.#trait_method_ident(
+                            &#krate::WSLSessionInformation::from(session_ptr),
+                            &#krate::OfflineDistributionInformation::from(distribution_ptr),
+                        ).into()
+                    } else {
+                        #krate::plugin_not_initialized(#name)
+                    }
+                };
+                #guarded
             }
         }),
     };
 
     Ok(ok_result)
 }
+
+// WSL only takes an error message from the hooks able to fail the VM or distribution start
+fn plugin_error_allowed(hook: Hooks) -> bool {
+    matches!(hook, Hooks::OnVMStarted | Hooks::OnDistributionStarted)
+}
+
+// Calls the `body` closure so that a panic does not unwind into WSL
+pub(super) fn guard_tokens(
+    name: &str,
+    plugin_error_allowed: bool,
+    args: &PluginArgs,
+) -> TokenStream {
+    let krate = &args.krate;
+    match args.panic {
+        PanicStrategy::Catch => {
+            let api = if plugin_error_allowed {
+                quote!(PLUGIN_API.get().copied())
+            } else {
+                quote!(::std::option::Option::None)
+            };
+            quote!(#krate::catch_hook_panic(#name, #api, body))
+        }
+        PanicStrategy::Abort => quote!(#krate::abort_on_hook_panic(#name, body)),
+    }
+}
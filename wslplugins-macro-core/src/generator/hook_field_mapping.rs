@@ -2,30 +2,32 @@ use super::utils;
 use crate::{
     generator::c_funcs_tokens,
     hooks::Hooks,
-    parser::{ParsedImpl, RequiredVersion},
+    parser::{ParsedImpl, PluginArgs, RequiredVersion},
 };
 use proc_macro2::{Span, TokenStream};
 use quote::{format_ident, quote};
 use syn::{parse_str, Ident, Lifetime, Result, Type};
 
+// Symbol defined twice when `wsl_plugin_v1` is used twice in a crate
+const SINGLE_PLUGIN_MARKER: &str = "wsl_plugin_v1_may_only_be_used_once_per_crate";
+
 // Main function to generate the complete TokenStream for the plugin
-pub fn generate(imp: &ParsedImpl, version: &RequiredVersion) -> Result<TokenStream> {
-    let entry_point: TokenStream = generate_entry_point(imp, version)?;
-    let hooks_funcs = generate_hook_fns(imp.hooks.as_ref())?;
+pub fn generate(imp: &ParsedImpl, args: &PluginArgs) -> Result<TokenStream> {
+    let entry_point: TokenStream = generate_entry_point(imp, args)?;
+    let hooks_funcs = generate_hook_fns(imp.hooks.as_ref(), args)?;
     Ok(quote! {
         #entry_point
         #(#hooks_funcs)*
     })
 }
 
-fn generate_hook_fns(hooks: &[Hooks]) -> Result<Vec<TokenStream>> {
+fn generate_hook_fns(hooks: &[Hooks], args: &PluginArgs) -> Result<Vec<TokenStream>> {
     hooks
         .iter()
         .map(|&mapping| {
-            let ts = c_funcs_tokens::get_c_func_tokens(mapping)?.expect(&format!(
-                "{:?} does not match with predefined C hook value",
-                mapping
-            ));
+            let ts = c_funcs_tokens::get_c_func_tokens(mapping, args)?.unwrap_or_else(|| {
+                panic!("{:?} does not match with predefined C hook value", mapping)
+            });
             Ok(ts)
         })
         .collect::<Result<Vec<TokenStream>>>()
@@ -61,37 +63,59 @@ fn hook_field_mapping(hooks_struct_name: &Ident, hook: Hooks) -> Result<TokenStr
 }
 
 // Generates the plugin entry function with hook management
-fn generate_entry_point(imp: &ParsedImpl, version: &RequiredVersion) -> Result<TokenStream> {
-    let static_plugin_type = create_static_type(&imp)?;
+fn generate_entry_point(imp: &ParsedImpl, args: &PluginArgs) -> Result<TokenStream> {
+    let static_plugin_type = create_static_type(imp)?;
+    // WSL takes no error message from the entry point
+    let guarded = c_funcs_tokens::guard_tokens("create_plugin", false, args);
+    let krate = &args.krate;
+    let entry_point = &args.entry_point;
+    let init_logger = args.logger.as_ref().map(|logger| quote!(#logger()?;));
     let hooks_ref_name = format_ident!("hooks_ref");
     let hook_set = prepare_hooks(&hooks_ref_name, &imp.hooks)?;
     let RequiredVersion {
         major,
         minor,
         revision,
-    } = version;
+    } = args.version;
 
     Ok(quote! {
         static PLUGIN: ::std::sync::OnceLock<#static_plugin_type> = ::std::sync::OnceLock::new();
-        #[no_mangle]
+        // Kept to report panics through `PluginError`
+        static PLUGIN_API: ::std::sync::OnceLock<&'static #krate::sys::WSLPluginAPIV1> = ::std::sync::OnceLock::new();
+        // Exported on every target under a fixed name, whatever the entry point is called, so that
+        // a second plugin in the crate fails to build
+        #[doc(hidden)]
+        #[export_name = #SINGLE_PLUGIN_MARKER]
+        static WSL_PLUGIN_V1_MARKER: u8 = 0;
+        #[cfg(windows)]
+        #[export_name = #entry_point]
         pub extern "C" fn WSLPluginAPIV1_EntryPoint(
-            api: *const ::wslplugins_rs::sys::WSLPluginAPIV1,
-            hooks: *mut ::wslplugins_rs::sys::WSLPluginHooksV1,
-        ) -> ::windows::core::HRESULT {
-            unsafe {
-                let api_ref: &'static ::wslplugins_rs::sys::WSLPluginAPIV1 = &*api;
-                let #hooks_ref_name: &mut ::wslplugins_rs::sys::WSLPluginHooksV1 = &mut *hooks;
+            api: *const #krate::sys::WSLPluginAPIV1,
+            hooks: *mut #krate::sys::WSLPluginHooksV1,
+        ) -> #krate::windows::core::HRESULT {
+            let body = || {
+                let Some(api_ref) = (unsafe { api.as_ref() }) else {
+                    return #krate::null_hook_argument("create_plugin", "api");
+                };
+                let Some(#hooks_ref_name) = (unsafe { hooks.as_mut() }) else {
+                    return #krate::null_hook_argument("create_plugin", "hooks");
+                };
                 create_plugin(api_ref, #hooks_ref_name).into()
-            }
+            };
+            #guarded
         }
 
+        // Only reachable through the entry point, which WSL calls on Windows
+        #[cfg_attr(not(windows), allow(dead_code))]
         fn create_plugin(
-            api: &'static ::wslplugins_rs::sys::WSLPluginAPIV1,
-            hooks_ref: &mut ::wslplugins_rs::sys::WSLPluginHooksV1,
-        ) -> ::windows::core::Result<()> {
-            let plugin: #static_plugin_type = create_plugin_with_required_version(api, #major, #minor, #revision)?;
+            api: &'static #krate::sys::WSLPluginAPIV1,
+            hooks_ref: &mut #krate::sys::WSLPluginHooksV1,
+        ) -> #krate::windows::core::Result<()> {
+            #init_logger
+            let _ = PLUGIN_API.set(api);
+            let plugin: #static_plugin_type = #krate::create_named_plugin_with_required_version(api, env!("CARGO_PKG_NAME"), #major, #minor, #revision)?;
             #(#hook_set)*
-            PLUGIN.set(plugin).map_err(|_| ::windows::core::Error::from(::windows::Win32::Foundation::E_ABORT))
+            PLUGIN.set(plugin).map_err(|_| #krate::windows::core::Error::from(#krate::windows::Win32::Foundation::E_ABORT))
         }
     })
 }
@@ -144,11 +168,154 @@ mod tests {
     #[test]
     fn test_generate_hook_fns() {
         let hooks = vec![Hooks::OnVMStarted];
-        let result = generate_hook_fns(&hooks);
+        let result = generate_hook_fns(&hooks, &parse_quote!(2, 0, 5));
         assert!(result.is_ok());
         let result = result.unwrap();
         assert_eq!(result.len(), 1);
         let result_str = result.first().to_token_stream().to_string();
         assert!(result_str.contains("extern \"C\" fn on_vm_started"));
+        assert!(result_str.contains(
+            &quote!(::wslplugins_rs::null_hook_argument(
+                "on_vm_started",
+                "session"
+            ))
+            .to_string()
+        ));
+        assert!(result_str.contains(
+            &quote!(::wslplugins_rs::plugin_not_initialized("on_vm_started")).to_string()
+        ));
+    }
+
+    #[test]
+    fn test_generate_hook_fns_catch_panics() {
+        let hooks = [Hooks::OnVMStarted, Hooks::OnVMStopping];
+        let result = generate_hook_fns(&hooks, &parse_quote!(2, 0, 5)).unwrap();
+        assert!(result[0].to_string().contains(
+            &quote!(::wslplugins_rs::catch_hook_panic(
+                "on_vm_started",
+                PLUGIN_API.get().copied(),
+                body
+            ))
+            .to_string()
+        ));
+        // No error message can be given to WSL when the VM stops
+        assert!(result[1].to_string().contains(
+            &quote!(::wslplugins_rs::catch_hook_panic(
+                "on_vm_stopping",
+                ::std::option::Option::None,
+                body
+            ))
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_generate_abort_on_panic() {
+        let imp = ParsedImpl {
+            target_type: parse_quote! { Plugin },
+            trait_: parse_quote! { WSLPluginV1<'a> },
+            hooks: Box::new([Hooks::OnVMStarted]),
+        };
+        let args: PluginArgs = parse_quote!(2, 0, 5, panic = "abort");
+        let result = generate(&imp, &args).unwrap().to_string();
+        assert!(result.contains(
+            &quote!(::wslplugins_rs::abort_on_hook_panic("create_plugin", body)).to_string()
+        ));
+        assert!(result.contains(
+            &quote!(::wslplugins_rs::abort_on_hook_panic("on_vm_started", body)).to_string()
+        ));
+        assert!(!result.contains("catch_hook_panic"));
+    }
+
+    #[test]
+    fn test_create_plugin_panic_is_not_reported_to_wsl() {
+        let imp = ParsedImpl {
+            target_type: parse_quote! { Plugin },
+            trait_: parse_quote! { WSLPluginV1<'a> },
+            hooks: Box::new([]),
+        };
+        let result = generate(&imp, &parse_quote!(2, 0, 5)).unwrap().to_string();
+        assert!(result.contains(
+            &quote!(::wslplugins_rs::catch_hook_panic(
+                "create_plugin",
+                ::std::option::Option::None,
+                body
+            ))
+            .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_generate_named_args() {
+        let imp = ParsedImpl {
+            target_type: parse_quote! { Plugin },
+            trait_: parse_quote! { WSLPluginV1<'a> },
+            hooks: Box::new([Hooks::OnVMStopping]),
+        };
+        let args: PluginArgs = parse_quote!(
+            min_version = "2.1.2",
+            logger = logging::init,
+            entry_point = "PluginEntry",
+            crate = crate::reexport::wslplugins_rs,
+        );
+        let result = generate(&imp, &args).unwrap().to_string();
+        assert!(result.contains(&quote!(#[export_name = "PluginEntry"]).to_string()));
+        assert!(result.contains(&quote!(logging::init()?;).to_string()));
+        assert!(result.contains(
+            &quote!(
+                crate::reexport::wslplugins_rs::create_named_plugin_with_required_version(
+                    api,
+                    env!("CARGO_PKG_NAME"),
+                    2u32,
+                    1u32,
+                    2u32
+                )
+            )
+            .to_string()
+        ));
+        // Every path goes through the re-export
+        assert_eq!(
+            result.matches(":: wslplugins_rs ::").count(),
+            result.matches("reexport :: wslplugins_rs ::").count()
+        );
+    }
+
+    // WSL only looks up `WSLPluginAPIV1_EntryPoint`, the export is only renamed when asked
+    #[test]
+    fn test_generate_entry_point_name() {
+        let imp = ParsedImpl {
+            target_type: parse_quote! { Plugin },
+            trait_: parse_quote! { WSLPluginV1<'a> },
+            hooks: Box::new([]),
+        };
+        let entry_point = |export_name: &str| {
+            quote!(
+                #[cfg(windows)]
+                #[export_name = #export_name]
+                pub extern "C" fn WSLPluginAPIV1_EntryPoint
+            )
+            .to_string()
+        };
+        let result = generate(&imp, &parse_quote!(2, 0, 5)).unwrap().to_string();
+        assert!(result.contains(&entry_point("WSLPluginAPIV1_EntryPoint")));
+        let result = generate(&imp, &parse_quote!(2, 0, 5, entry_point = "PluginEntry"))
+            .unwrap()
+            .to_string();
+        assert!(result.contains(&entry_point("PluginEntry")));
+        assert!(!result.contains(&entry_point("WSLPluginAPIV1_EntryPoint")));
+    }
+
+    #[test]
+    fn test_logger_runs_first() {
+        let imp = ParsedImpl {
+            target_type: parse_quote! { Plugin },
+            trait_: parse_quote! { WSLPluginV1<'a> },
+            hooks: Box::new([]),
+        };
+        let result = generate(&imp, &parse_quote!(2, 0, 5, logger = logging::init))
+            .unwrap()
+            .to_string();
+        let position = |tokens: TokenStream| result.find(&tokens.to_string()).unwrap();
+        assert!(position(quote!(logging::init()?;)) < position(quote!(PLUGIN_API.set(api))));
     }
 }
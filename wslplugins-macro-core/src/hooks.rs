@@ -1,3 +1,4 @@
+use crate::parser::RequiredVersion;
 use heck::ToSnakeCase;
 use strum::IntoEnumIterator;
 include!(concat!(env!("OUT_DIR"), "/hooks.rs"));
@@ -15,6 +16,17 @@ impl Hooks {
         self.to_string().to_snake_case()
     }
 
+    /// WSL version bringing the hook, `None` for the ones available since the first plugin API.
+    pub fn introduced_in(&self) -> Option<RequiredVersion> {
+        match self {
+            #[cfg(distribution_registration_hooks)]
+            Hooks::OnDistributionRegistered | Hooks::OnDistributionUnregistered => {
+                Some(RequiredVersion::new(2, 1, 2))
+            }
+            _ => None,
+        }
+    }
+
     pub fn from_trait_method_name(trait_method_name: impl AsRef<str>) -> Option<Hooks> {
         Hooks::iter().find(|hook| hook.get_trait_method_name() == trait_method_name.as_ref())
     }
@@ -36,14 +48,17 @@ mod tests {
             Hooks::OnDistributionStopping.get_c_method_name(),
             "on_distribution_stopping"
         );
-        assert_eq!(
-            Hooks::OnDistributionRegistered.get_c_method_name(),
-            "on_distribution_registered"
-        );
-        assert_eq!(
-            Hooks::OnDistributionUnregistered.get_c_method_name(),
-            "on_distribution_unregistered"
-        );
+        #[cfg(distribution_registration_hooks)]
+        {
+            assert_eq!(
+                Hooks::OnDistributionRegistered.get_c_method_name(),
+                "on_distribution_registered"
+            );
+            assert_eq!(
+                Hooks::OnDistributionUnregistered.get_c_method_name(),
+                "on_distribution_unregistered"
+            );
+        }
     }
 
     #[test]
@@ -58,14 +73,17 @@ mod tests {
             Hooks::OnDistributionStopping.get_hook_field_name(),
             "OnDistributionStopping"
         );
-        assert_eq!(
-            Hooks::OnDistributionRegistered.get_hook_field_name(),
-            "OnDistributionRegistered"
-        );
-        assert_eq!(
-            Hooks::OnDistributionUnregistered.get_hook_field_name(),
-            "OnDistributionUnregistered"
-        );
+        #[cfg(distribution_registration_hooks)]
+        {
+            assert_eq!(
+                Hooks::OnDistributionRegistered.get_hook_field_name(),
+                "OnDistributionRegistered"
+            );
+            assert_eq!(
+                Hooks::OnDistributionUnregistered.get_hook_field_name(),
+                "OnDistributionUnregistered"
+            );
+        }
     }
 
     #[test]
@@ -83,13 +101,27 @@ mod tests {
             Hooks::OnDistributionStopping.get_trait_method_name(),
             "on_distribution_stopping"
         );
+        #[cfg(distribution_registration_hooks)]
+        {
+            assert_eq!(
+                Hooks::OnDistributionRegistered.get_trait_method_name(),
+                "on_distribution_registered"
+            );
+            assert_eq!(
+                Hooks::OnDistributionUnregistered.get_trait_method_name(),
+                "on_distribution_unregistered"
+            );
+        }
+    }
+
+    #[test]
+    fn test_introduced_in() {
+        assert_eq!(Hooks::OnVMStarted.introduced_in(), None);
+        assert_eq!(Hooks::OnDistributionStopping.introduced_in(), None);
+        #[cfg(distribution_registration_hooks)]
         assert_eq!(
-            Hooks::OnDistributionRegistered.get_trait_method_name(),
-            "on_distribution_registered"
-        );
-        assert_eq!(
-            Hooks::OnDistributionUnregistered.get_trait_method_name(),
-            "on_distribution_unregistered"
+            Hooks::OnDistributionRegistered.introduced_in(),
+            Some(RequiredVersion::new(2, 1, 2))
         );
     }
 
@@ -111,14 +143,19 @@ mod tests {
             Hooks::from_trait_method_name("on_distribution_stopping").unwrap(),
             Hooks::OnDistributionStopping
         );
-        assert_eq!(
-            Hooks::from_trait_method_name("on_distribution_registered").unwrap(),
-            Hooks::OnDistributionRegistered
-        );
-        assert_eq!(
-            Hooks::from_trait_method_name("on_distribution_unregistered").unwrap(),
-            Hooks::OnDistributionUnregistered
-        );
+        #[cfg(distribution_registration_hooks)]
+        {
+            assert_eq!(
+                Hooks::from_trait_method_name("on_distribution_registered").unwrap(),
+                Hooks::OnDistributionRegistered
+            );
+            assert_eq!(
+                Hooks::from_trait_method_name("on_distribution_unregistered").unwrap(),
+                Hooks::OnDistributionUnregistered
+            );
+        }
+        #[cfg(not(distribution_registration_hooks))]
+        assert!(Hooks::from_trait_method_name("on_distribution_registered").is_none());
         assert!(Hooks::from_trait_method_name("invalid_method_name").is_none());
     }
 }
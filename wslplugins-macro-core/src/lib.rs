@@ -2,40 +2,35 @@ pub(crate) mod generator;
 pub(crate) mod hooks;
 pub(crate) mod parser;
 pub(crate) mod utils;
-
-use std::sync::atomic::{AtomicBool, Ordering};
+pub(crate) mod version_check;
 
 use generator::generate;
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{parse2, Error, Result};
+use syn::{parse2, ItemImpl, Result};
 
-use crate::parser::{ParsedImpl, RequiredVersion};
-static ALREADY_USED: AtomicBool = AtomicBool::new(false);
+use crate::parser::{ParsedImpl, PluginArgs};
+use crate::version_check::check_versions;
 
+// Keeps no state: one process may expand the plugins of several crates, a second use in a crate
+// is reported by the compiler through the marker symbol of the generated code
 pub fn wsl_plugin_v1(attr: TokenStream, item: TokenStream) -> Result<TokenStream> {
-    let already_used_result = match ALREADY_USED.swap(true, Ordering::SeqCst) {
-        true => Err(Error::new_spanned(
-            &attr,
-            "This attribute can be used only one time per crate.",
-        )),
-        false => Ok(()),
-    };
-
     let parsed_impl_result = parse2::<ParsedImpl>(item.clone());
-    let required_version_result = parse2::<RequiredVersion>(attr);
-    let (_, parsed_impl, required_version) = acc_syn_result!(
-        already_used_result,
-        parsed_impl_result,
-        required_version_result,
-    )?;
-    let generated_tokens = generate(&parsed_impl, &required_version)?;
+    let args_result = parse2::<PluginArgs>(attr);
+    let (parsed_impl, args) = acc_syn_result!(parsed_impl_result, args_result)?;
+    // Reported next to the generated code, so the impl itself is still checked
+    let version_diagnostics = check_versions(
+        &parse2::<ItemImpl>(item.clone())?,
+        &args.version,
+        args.version_check,
+    );
+    let generated_tokens = generate(&parsed_impl, &args)?;
 
     Ok(quote! {
         #item
         #generated_tokens
-    }
-    .into())
+        #version_diagnostics
+    })
 }
 
 #[cfg(test)]
@@ -155,4 +150,22 @@ mod test {
         assert!(result.is_ok());
         eprint!("{}", result.unwrap())
     }
+
+    // rust-analyzer and incremental builds expand the plugins of several crates in one process
+    #[test]
+    fn test_expansions_are_independent() {
+        let item = quote! {
+            impl<'a> WSLPluginV1<'a> for Plugin {
+                fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+                    Ok(Plugin)
+                }
+            }
+        };
+        for _ in 0..2 {
+            let expanded = wsl_plugin_v1(quote! {2, 0, 5}, item.clone())
+                .unwrap()
+                .to_string();
+            assert!(expanded.contains("wsl_plugin_v1_may_only_be_used_once_per_crate"));
+        }
+    }
 }
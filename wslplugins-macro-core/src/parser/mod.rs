@@ -1,4 +1,6 @@
 pub(crate) mod parsed_impl;
+pub(crate) mod plugin_args;
 pub(crate) mod required_version;
 pub(crate) use parsed_impl::ParsedImpl;
+pub(crate) use plugin_args::{PanicStrategy, PluginArgs, VersionCheck};
 pub(crate) use required_version::RequiredVersion;
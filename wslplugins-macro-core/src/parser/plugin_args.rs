@@ -0,0 +1,347 @@
+use proc_macro2::Span;
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_quote, Error, Expr, Ident, LitInt, LitStr, Path, Result, Token};
+
+use crate::parser::RequiredVersion;
+
+/// What the generated functions do when the plugin panics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PanicStrategy {
+    /// Returns `E_UNEXPECTED` to WSL.
+    #[default]
+    Catch,
+    /// Aborts the WSL service.
+    Abort,
+}
+
+/// How the macro reports a hook or an `ApiV1` method newer than the required version.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionCheck {
+    #[default]
+    Error,
+    Warn,
+}
+
+/// Arguments of the attribute: the required version, given first as `major, minor[, revision]`
+/// or as `min_version = "major.minor[.revision]"`, then the options.
+#[derive(Debug)]
+pub struct PluginArgs {
+    pub version: RequiredVersion,
+    pub panic: PanicStrategy,
+    pub version_check: VersionCheck,
+    /// Called by the generated code before anything else, e.g. to set up logging.
+    pub logger: Option<Path>,
+    /// Name of the exported entry point.
+    pub entry_point: String,
+    /// Path of the `wslplugins_rs` crate in the generated code.
+    pub krate: Path,
+}
+
+const DEFAULT_ENTRY_POINT: &str = "WSLPluginAPIV1_EntryPoint";
+
+fn set_once<T>(slot: &mut Option<T>, name: &Ident, value: T) -> Result<()> {
+    if slot.is_some() {
+        Err(Error::new_spanned(
+            name,
+            format!("duplicate `{}` option", name),
+        ))
+    } else {
+        *slot = Some(value);
+        Ok(())
+    }
+}
+
+fn parse_panic(value: &LitStr) -> Result<PanicStrategy> {
+    match value.value().as_str() {
+        "catch" => Ok(PanicStrategy::Catch),
+        "abort" => Ok(PanicStrategy::Abort),
+        _ => Err(Error::new_spanned(
+            value,
+            "expected `\"catch\"` or `\"abort\"`",
+        )),
+    }
+}
+
+fn parse_version_check(value: &LitStr) -> Result<VersionCheck> {
+    match value.value().as_str() {
+        "error" => Ok(VersionCheck::Error),
+        "warn" => Ok(VersionCheck::Warn),
+        _ => Err(Error::new_spanned(
+            value,
+            "expected `\"error\"` or `\"warn\"`",
+        )),
+    }
+}
+
+fn parse_entry_point(value: &LitStr) -> Result<String> {
+    let name = value.value();
+    let mut chars = name.chars();
+    let is_c_identifier = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_c_identifier {
+        Ok(name)
+    } else {
+        Err(Error::new_spanned(
+            value,
+            "expected a C identifier such as `\"WSLPluginAPIV1_EntryPoint\"`",
+        ))
+    }
+}
+
+impl Parse for PluginArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        // Errors of the option values are all reported, a malformed list stops the parsing
+        let mut errors: Option<Error> = None;
+        let mut report = |error: Error| match errors.as_mut() {
+            Some(errors) => errors.combine(error),
+            None => errors = Some(error),
+        };
+        let mut positional_version = None;
+        if input.peek(LitInt) {
+            positional_version = Some(input.parse::<RequiredVersion>()?);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        let mut min_version = None;
+        // An invalid `min_version` is already reported
+        let mut min_version_given = false;
+        let mut panic = None;
+        let mut version_check = None;
+        let mut logger = None;
+        let mut entry_point = None;
+        let mut krate = None;
+        while !input.is_empty() {
+            // `crate` is a keyword
+            let name = Ident::parse_any(input)?;
+            input.parse::<Token![=]>()?;
+            let result = match name.to_string().as_str() {
+                "min_version" => {
+                    min_version_given = true;
+                    let value = input.parse::<LitStr>()?;
+                    RequiredVersion::from_lit_str(&value).and_then(|version| {
+                        if positional_version.is_some() {
+                            Err(Error::new_spanned(
+                                &name,
+                                "the version is already given before the options",
+                            ))
+                        } else {
+                            set_once(&mut min_version, &name, version)
+                        }
+                    })
+                }
+                "panic" => {
+                    let value = input.parse::<LitStr>()?;
+                    parse_panic(&value).and_then(|value| set_once(&mut panic, &name, value))
+                }
+                "version_check" => {
+                    let value = input.parse::<LitStr>()?;
+                    parse_version_check(&value)
+                        .and_then(|value| set_once(&mut version_check, &name, value))
+                }
+                "logger" => {
+                    let value = input.parse::<Path>()?;
+                    set_once(&mut logger, &name, value)
+                }
+                "entry_point" => {
+                    let value = input.parse::<LitStr>()?;
+                    parse_entry_point(&value)
+                        .and_then(|value| set_once(&mut entry_point, &name, value))
+                }
+                "crate" => {
+                    let value = input.parse::<Path>()?;
+                    set_once(&mut krate, &name, value)
+                }
+                _ => {
+                    // Skipped so the options after it are still checked
+                    input.parse::<Expr>()?;
+                    Err(Error::new_spanned(
+                        &name,
+                        format!(
+                            "unknown option `{}`, expected `min_version`, `panic`, \
+                             `version_check`, `logger`, `entry_point` or `crate`",
+                            name
+                        ),
+                    ))
+                }
+            };
+            if let Err(error) = result {
+                report(error);
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        let version = positional_version.or(min_version);
+        if version.is_none() && !min_version_given {
+            report(Error::new(
+                Span::call_site(),
+                "missing the required version, e.g. `min_version = \"2.0.5\"`",
+            ));
+        }
+        match (errors, version) {
+            (None, Some(version)) => Ok(PluginArgs {
+                version,
+                panic: panic.unwrap_or_default(),
+                version_check: version_check.unwrap_or_default(),
+                logger,
+                entry_point: entry_point.unwrap_or_else(|| DEFAULT_ENTRY_POINT.to_owned()),
+                krate: krate.unwrap_or_else(|| parse_quote!(::wslplugins_rs)),
+            }),
+            (errors, _) => Err(errors.expect("a missing version is reported")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::{quote, ToTokens};
+    use syn::parse2;
+
+    #[test]
+    fn test_parse_version_only() {
+        let args: PluginArgs = parse2(quote! { 2, 1, 2 }).unwrap();
+        assert_eq!(args.version.revision, 2);
+        assert_eq!(args.panic, PanicStrategy::Catch);
+    }
+
+    #[test]
+    fn test_parse_panic() {
+        let args: PluginArgs = parse2(quote! { 2, 1, panic = "abort" }).unwrap();
+        assert_eq!(args.version.minor, 1);
+        assert_eq!(args.version.revision, 0);
+        assert_eq!(args.panic, PanicStrategy::Abort);
+        let args: PluginArgs = parse2(quote! { 2, 1, 2, panic = "catch" }).unwrap();
+        assert_eq!(args.version.revision, 2);
+        assert_eq!(args.panic, PanicStrategy::Catch);
+    }
+
+    #[test]
+    fn test_parse_version_check() {
+        let args: PluginArgs = parse2(quote! { 2, 0, 5 }).unwrap();
+        assert_eq!(args.version_check, VersionCheck::Error);
+        let args: PluginArgs =
+            parse2(quote! { 2, 0, 5, version_check = "warn", panic = "abort" }).unwrap();
+        assert_eq!(args.version_check, VersionCheck::Warn);
+        assert_eq!(args.panic, PanicStrategy::Abort);
+        let err = parse2::<PluginArgs>(quote! { 2, 0, 5, version_check = "off" }).unwrap_err();
+        assert_eq!(err.to_string(), "expected `\"error\"` or `\"warn\"`");
+    }
+
+    #[test]
+    fn test_parse_invalid_panic() {
+        let err = parse2::<PluginArgs>(quote! { 2, 1, panic = "unwind" }).unwrap_err();
+        assert_eq!(err.to_string(), "expected `\"catch\"` or `\"abort\"`");
+        let err =
+            parse2::<PluginArgs>(quote! { 2, 1, panic = "abort", panic = "abort" }).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate `panic` option");
+    }
+
+    #[test]
+    fn test_parse_unknown_option() {
+        let err = parse2::<PluginArgs>(quote! { 2, 1, unwind = "abort" }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown option `unwind`, expected `min_version`, `panic`, `version_check`, \
+             `logger`, `entry_point` or `crate`"
+        );
+    }
+
+    #[test]
+    fn test_parse_named_args() {
+        let args: PluginArgs = parse2(quote! {
+            min_version = "2.1.2",
+            panic = "abort",
+            logger = crate::logging::init,
+            entry_point = "PluginEntry",
+            crate = ::my_reexport::wslplugins_rs,
+        })
+        .unwrap();
+        assert_eq!(args.version, RequiredVersion::new(2, 1, 2));
+        assert_eq!(args.panic, PanicStrategy::Abort);
+        assert_eq!(
+            args.logger.to_token_stream().to_string(),
+            quote!(crate::logging::init).to_string()
+        );
+        assert_eq!(args.entry_point, "PluginEntry");
+        assert_eq!(
+            args.krate.to_token_stream().to_string(),
+            quote!(::my_reexport::wslplugins_rs).to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let args: PluginArgs = parse2(quote! { min_version = "2.0" }).unwrap();
+        assert_eq!(args.version, RequiredVersion::new(2, 0, 0));
+        assert!(args.logger.is_none());
+        assert_eq!(args.entry_point, "WSLPluginAPIV1_EntryPoint");
+        assert_eq!(
+            args.krate.to_token_stream().to_string(),
+            quote!(::wslplugins_rs).to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_positional_version_with_named_options() {
+        let args: PluginArgs = parse2(quote! { 2, 0, 5, crate = reexport::wslplugins_rs }).unwrap();
+        assert_eq!(args.version, RequiredVersion::new(2, 0, 5));
+        assert_eq!(
+            args.krate.to_token_stream().to_string(),
+            quote!(reexport::wslplugins_rs).to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_version_errors() {
+        let err = parse2::<PluginArgs>(quote! { panic = "abort" }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing the required version, e.g. `min_version = \"2.0.5\"`"
+        );
+        let err = parse2::<PluginArgs>(quote! { 2, 0, 5, min_version = "2.0.5" }).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the version is already given before the options"
+        );
+        let err = parse2::<PluginArgs>(quote! { min_version = "2.0.5", min_version = "2.1.2" })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "duplicate `min_version` option");
+    }
+
+    #[test]
+    fn test_parse_invalid_entry_point() {
+        for name in ["", "1entry", "entry-point", "entry point"] {
+            let err = parse2::<PluginArgs>(quote! { 2, 0, 5, entry_point = #name }).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "expected a C identifier such as `\"WSLPluginAPIV1_EntryPoint\"`"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_errors_are_combined() {
+        let err = parse2::<PluginArgs>(quote! {
+            min_version = "two",
+            panic = "unwind",
+            colour = blue,
+            entry_point = "my-entry",
+        })
+        .unwrap_err();
+        let messages: Vec<_> = err.into_iter().map(|err| err.to_string()).collect();
+        // The version is not reported missing on top of being invalid
+        assert_eq!(messages.len(), 4);
+        assert_eq!(
+            messages[0],
+            "expected a version such as \"2.1.2\" or \"2.1\""
+        );
+        assert_eq!(messages[1], "expected `\"catch\"` or `\"abort\"`");
+        assert!(messages[2].starts_with("unknown option `colour`"));
+        assert!(messages[3].starts_with("expected a C identifier"));
+    }
+}
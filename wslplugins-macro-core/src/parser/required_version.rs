@@ -1,16 +1,52 @@
+use std::fmt;
+use syn::ext::IdentExt;
 use syn::parse::{Parse, ParseStream};
-use syn::LitInt;
+use syn::{Error, Ident, LitInt, LitStr};
 use syn::{Result, Token};
 
 use crate::acc_syn_result;
 
-#[derive(Debug)]
-
+// Ordered by major, then minor, then revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RequiredVersion {
     pub major: u32,
     pub minor: u32,
     pub revision: u32,
 }
+
+impl RequiredVersion {
+    pub const fn new(major: u32, minor: u32, revision: u32) -> Self {
+        Self {
+            major,
+            minor,
+            revision,
+        }
+    }
+
+    /// Parses the `"major.minor[.revision]"` form given to `min_version`.
+    pub fn from_lit_str(lit: &LitStr) -> Result<Self> {
+        let value = lit.value();
+        let components = value
+            .split('.')
+            .map(|component| component.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>();
+        match components.as_deref() {
+            Some(&[major, minor]) => Ok(Self::new(major, minor, 0)),
+            Some(&[major, minor, revision]) => Ok(Self::new(major, minor, revision)),
+            _ => Err(Error::new_spanned(
+                lit,
+                "expected a version such as \"2.1.2\" or \"2.1\"",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RequiredVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.revision)
+    }
+}
+
 impl Parse for RequiredVersion {
     fn parse(input: ParseStream) -> Result<Self> {
         // Result of parsing the major version to u32
@@ -20,13 +56,14 @@ impl Parse for RequiredVersion {
         // Result of parsing the minor version to u32
         let minor_lit = input.parse::<LitInt>()?;
         // Parse the revision if it exists
-        let revision_lit = if input.peek(Token![,]) {
+        let revision_lit = if input.peek(Token![,]) && !input.peek2(Ident::peek_any) {
             input.parse::<Token![,]>().unwrap();
             Some(input.parse::<LitInt>()?)
         } else {
             None
         };
-        if input.is_empty() {
+        // Options of the attribute may follow, e.g. `, panic = "abort"`
+        if input.is_empty() || (input.peek(Token![,]) && input.peek2(Ident::peek_any)) {
             Ok(())
         } else {
             Err(input.error("unexpected additional components in version"))
@@ -50,7 +87,24 @@ impl Parse for RequiredVersion {
 mod tests {
     use super::*;
     use quote::quote;
-    use syn::parse2;
+    use syn::{parse2, parse_quote};
+
+    #[test]
+    fn test_from_lit_str() {
+        let version = RequiredVersion::from_lit_str(&parse_quote!("2.1.2")).unwrap();
+        assert_eq!(version, RequiredVersion::new(2, 1, 2));
+        let version = RequiredVersion::from_lit_str(&parse_quote!("2.1")).unwrap();
+        assert_eq!(version, RequiredVersion::new(2, 1, 0));
+        for invalid in ["", "2", "2.1.2.0", "2.x", "2..1", " 2.1"] {
+            let lit = LitStr::new(invalid, proc_macro2::Span::call_site());
+            assert_eq!(
+                RequiredVersion::from_lit_str(&lit).unwrap_err().to_string(),
+                "expected a version such as \"2.1.2\" or \"2.1\"",
+                "{}",
+                invalid
+            );
+        }
+    }
 
     #[test]
     fn test_parse_valid_version_with_revision() {
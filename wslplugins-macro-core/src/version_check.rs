@@ -0,0 +1,240 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::visit::{self, Visit};
+use syn::{Error, Expr, ExprCall, ExprMethodCall, ImplItem, ItemImpl};
+
+use crate::hooks::Hooks;
+use crate::parser::{RequiredVersion, VersionCheck};
+
+/// Methods of `ApiV1` and `WslCommand` calling an entry point newer than the first plugin API.
+const GATED_METHODS: &[(&str, RequiredVersion)] = &[
+    (
+        "execute_binary_in_distribution",
+        RequiredVersion::new(2, 1, 2),
+    ),
+    (
+        "execute_binary_in_distribution_with_options",
+        RequiredVersion::new(2, 1, 2),
+    ),
+    (
+        "execute_binary_in_distribution_async",
+        RequiredVersion::new(2, 1, 2),
+    ),
+    ("in_distribution", RequiredVersion::new(2, 1, 2)),
+];
+
+fn gated_method(name: &str) -> Option<RequiredVersion> {
+    GATED_METHODS
+        .iter()
+        .find(|(method, _)| *method == name)
+        .map(|(_, version)| *version)
+}
+
+#[derive(Debug)]
+struct TooNew {
+    span: Span,
+    message: String,
+    // Hooks are known for sure, method calls only by name
+    exact: bool,
+}
+
+// Types whose associated functions are gated, when called through a path
+const GATED_TYPES: &[&str] = &["ApiV1", "WslCommand"];
+
+// Calls to the gated methods, found by name since types are not known to the macro: any method
+// with such a name matches, but a path call has to go through one of the `GATED_TYPES`
+struct GatedCalls<'a> {
+    required: &'a RequiredVersion,
+    found: Vec<TooNew>,
+}
+
+impl GatedCalls<'_> {
+    fn check(&mut self, name: &str, span: Span) {
+        if let Some(version) = gated_method(name).filter(|version| version > self.required) {
+            self.found.push(TooNew {
+                span,
+                message: format!(
+                    "`{}` requires WSL {}, but the plugin requires {}",
+                    name, version, self.required
+                ),
+                exact: false,
+            });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for GatedCalls<'_> {
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        self.check(&call.method.to_string(), call.method.span());
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        if let Expr::Path(path) = call.func.as_ref() {
+            let segments: Vec<_> = path.path.segments.iter().collect();
+            if let [.., ty, function] = segments[..] {
+                if GATED_TYPES.contains(&ty.ident.to_string().as_str()) {
+                    self.check(&function.ident.to_string(), function.ident.span());
+                }
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+}
+
+fn too_new(imp: &ItemImpl, required: &RequiredVersion) -> Vec<TooNew> {
+    let mut calls = GatedCalls {
+        required,
+        found: Vec::new(),
+    };
+    let mut hooks = Vec::new();
+    for item in &imp.items {
+        if let ImplItem::Fn(func) = item {
+            let name = func.sig.ident.to_string();
+            if let Some(version) = Hooks::from_trait_method_name(&name)
+                .and_then(|hook| hook.introduced_in())
+                .filter(|version| version > required)
+            {
+                hooks.push(TooNew {
+                    span: func.sig.ident.span(),
+                    message: format!(
+                        "the `{}` hook was introduced in WSL {}, but the plugin requires {}",
+                        name, version, required
+                    ),
+                    exact: true,
+                });
+            }
+            calls.visit_block(&func.block);
+        }
+    }
+    hooks.extend(calls.found);
+    hooks
+}
+
+fn warning(TooNew { span, message, .. }: TooNew) -> TokenStream {
+    // Stable proc macros cannot warn, so a deprecated item is used at the offending span
+    let usage = quote_spanned!(span=> RequiresNewerWsl);
+    quote! {
+        const _: () = {
+            #[deprecated(note = #message)]
+            struct RequiresNewerWsl;
+            let _ = #usage;
+        };
+    }
+}
+
+/// Reports the hooks and gated methods of `imp` newer than `required`, as tokens to emit next to
+/// the generated code.
+///
+/// Hooks are errors unless `mode` is [`VersionCheck::Warn`]. Methods are matched by name only, so
+/// they are always warnings, and only found in the body of the impl, not in the functions it
+/// calls.
+pub(crate) fn check_versions(
+    imp: &ItemImpl,
+    required: &RequiredVersion,
+    mode: VersionCheck,
+) -> TokenStream {
+    let (errors, warnings): (Vec<_>, Vec<_>) = too_new(imp, required)
+        .into_iter()
+        .partition(|too_new| too_new.exact && mode == VersionCheck::Error);
+    let errors = errors
+        .into_iter()
+        .map(|too_new| Error::new(too_new.span, too_new.message).to_compile_error());
+    errors.chain(warnings.into_iter().map(warning)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn plugin() -> ItemImpl {
+        parse_quote! {
+            impl<'a> WSLPluginV1<'a> for Plugin<'a> {
+                fn try_new(api: ApiV1<'a>) -> Result<Self> {
+                    Ok(Plugin { api })
+                }
+
+                fn on_distribution_registered(
+                    &self,
+                    session: &WSLSessionInformation,
+                    distribution: &OfflineDistributionInformation,
+                ) -> Result<()> {
+                    let mut command = self.api.command(session, "/bin/true");
+                    command.in_distribution(distribution.id());
+                    Ok(())
+                }
+
+                fn on_vm_started(
+                    &self,
+                    session: &WSLSessionInformation,
+                    _settings: &WSLVmCreationSettings,
+                ) -> Result<()> {
+                    ApiV1::execute_binary_in_distribution(&self.api, session, &ID, "/bin/true", &[])?;
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    #[cfg(distribution_registration_hooks)]
+    #[test]
+    fn test_too_new() {
+        let found = too_new(&plugin(), &RequiredVersion::new(2, 0, 5));
+        let messages: Vec<_> = found
+            .iter()
+            .map(|too_new| too_new.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "the `on_distribution_registered` hook was introduced in WSL 2.1.2, but the plugin requires 2.0.5",
+                "`in_distribution` requires WSL 2.1.2, but the plugin requires 2.0.5",
+                "`execute_binary_in_distribution` requires WSL 2.1.2, but the plugin requires 2.0.5",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recent_enough() {
+        assert!(too_new(&plugin(), &RequiredVersion::new(2, 1, 2)).is_empty());
+        assert!(check_versions(
+            &plugin(),
+            &RequiredVersion::new(2, 1, 3),
+            VersionCheck::Error
+        )
+        .is_empty());
+    }
+
+    #[cfg(distribution_registration_hooks)]
+    #[test]
+    fn test_check_versions() {
+        let required = RequiredVersion::new(2, 0, 5);
+        // Only the hook is known for sure to be too new
+        let reported = check_versions(&plugin(), &required, VersionCheck::Error).to_string();
+        assert_eq!(reported.matches("compile_error").count(), 1);
+        assert_eq!(reported.matches("deprecated").count(), 2);
+        let warnings = check_versions(&plugin(), &required, VersionCheck::Warn).to_string();
+        assert_eq!(warnings.matches("compile_error").count(), 0);
+        assert_eq!(warnings.matches("deprecated").count(), 3);
+    }
+
+    #[test]
+    fn test_functions_of_other_types() {
+        let imp: ItemImpl = parse_quote! {
+            impl<'a> WSLPluginV1<'a> for Plugin<'a> {
+                fn on_vm_stopping(&self, session: &WSLSessionInformation) -> Result<()> {
+                    in_distribution(session);
+                    helpers::execute_binary_in_distribution(session);
+                    Helper::in_distribution(session);
+                    wslplugins_rs::WslCommand::in_distribution(&mut self.command(), &ID);
+                    Ok(())
+                }
+            }
+        };
+        let found = too_new(&imp, &RequiredVersion::new(2, 0, 5));
+        assert_eq!(found.len(), 1);
+        assert!(!found[0].exact);
+        assert!(found[0].message.starts_with("`in_distribution`"));
+    }
+}
@@ -0,0 +1,55 @@
+//! Hooks called after `try_new` failed, which WSL should not do.
+use std::ptr;
+use wslplugins_rs::sys::{
+    WSLPluginAPIV1, WSLPluginHooksV1, WSLSessionInformation as NativeSession,
+    WSLVersion as NativeVersion,
+};
+use wslplugins_rs::windows::core::{Error, Result};
+use wslplugins_rs::windows::Win32::Foundation::{E_ABORT, HANDLE};
+use wslplugins_rs::windows::Win32::Security::PSID;
+use wslplugins_rs::*;
+
+struct Plugin;
+
+#[wsl_plugin_v1(2, 0, 5)]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+        Err(Error::from(E_ABORT))
+    }
+
+    fn on_vm_stopping(&self, _session: &WSLSessionInformation) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_hook_after_failed_init() {
+    let api: &'static WSLPluginAPIV1 = Box::leak(Box::new(WSLPluginAPIV1 {
+        Version: NativeVersion {
+            Major: 2,
+            Minor: 1,
+            Revision: 3,
+        },
+        MountFolder: None,
+        ExecuteBinary: None,
+        PluginError: None,
+        #[cfg(feature = "api-2-1-2")]
+        ExecuteBinaryInDistribution: None,
+    }));
+    let mut hooks: WSLPluginHooksV1 = unsafe { std::mem::zeroed() };
+    assert_eq!(create_plugin(api, &mut hooks).unwrap_err().code(), E_ABORT);
+
+    let session = NativeSession {
+        SessionId: 1,
+        UserToken: HANDLE(ptr::null_mut()),
+        UserSid: PSID(ptr::null_mut()),
+    };
+    assert_eq!(
+        WslError::from(on_vm_stopping(&session)),
+        WslError::IllegalMethodCall
+    );
+    assert_eq!(
+        WslError::from(on_vm_stopping(ptr::null())),
+        WslError::InvalidPointer
+    );
+}
@@ -0,0 +1,86 @@
+//! Calls the functions generated by `wsl_plugin_v1` the way WSL would, with invalid arguments.
+use std::ptr;
+use wslplugins_rs::sys::{
+    WSLDistributionInformation as NativeDistribution, WSLPluginAPIV1, WSLPluginHooksV1,
+    WSLSessionInformation as NativeSession, WSLVersion as NativeVersion,
+    WSLVmCreationSettings as NativeSettings,
+};
+use wslplugins_rs::windows::core::{Result, GUID, PCWSTR};
+use wslplugins_rs::windows::Win32::Foundation::{E_POINTER, HANDLE, S_OK};
+use wslplugins_rs::windows::Win32::Security::PSID;
+use wslplugins_rs::*;
+
+struct Plugin;
+
+#[wsl_plugin_v1(2, 0, 5)]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+        Ok(Plugin)
+    }
+
+    fn on_vm_started(
+        &self,
+        _session: &WSLSessionInformation,
+        _user_settings: &WSLVmCreationSettings,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn on_distribution_started(
+        &self,
+        _session: &WSLSessionInformation,
+        _distribution: &DistributionInformation,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+// A single test, the plugin is created once for the whole binary
+#[test]
+fn test_hook_diagnostics() {
+    let session = NativeSession {
+        SessionId: 1,
+        UserToken: HANDLE(ptr::null_mut()),
+        UserSid: PSID(ptr::null_mut()),
+    };
+    let settings = NativeSettings {
+        CustomConfigurationFlags: 0,
+    };
+    let distribution = NativeDistribution {
+        Id: GUID::zeroed(),
+        Name: PCWSTR::null(),
+        PidNamespace: 0,
+        PackageFamilyName: PCWSTR::null(),
+        InitPid: 1,
+    };
+
+    assert_eq!(
+        WslError::from(on_vm_started(&session, &settings)),
+        WslError::IllegalMethodCall
+    );
+
+    let api: &'static WSLPluginAPIV1 = Box::leak(Box::new(WSLPluginAPIV1 {
+        Version: NativeVersion {
+            Major: 2,
+            Minor: 1,
+            Revision: 3,
+        },
+        MountFolder: None,
+        ExecuteBinary: None,
+        PluginError: None,
+        #[cfg(feature = "api-2-1-2")]
+        ExecuteBinaryInDistribution: None,
+    }));
+    let mut hooks: WSLPluginHooksV1 = unsafe { std::mem::zeroed() };
+    create_plugin(api, &mut hooks).unwrap();
+
+    assert_eq!(on_vm_started(&session, &settings), S_OK);
+    assert_eq!(on_vm_started(ptr::null(), &settings), E_POINTER);
+    assert_eq!(on_vm_started(&session, ptr::null()), E_POINTER);
+    assert_eq!(on_distribution_started(&session, &distribution), S_OK);
+    assert_eq!(
+        on_distribution_started(ptr::null(), &distribution),
+        E_POINTER
+    );
+    assert_eq!(on_distribution_started(&session, ptr::null()), E_POINTER);
+}
@@ -4,6 +4,19 @@ mod test {
     #[test]
     fn test_macro_sucess() {
         let t = TestCases::new();
-        t.pass("tests/ui/success.rs")
+        t.pass("tests/ui/success.rs");
+        t.pass("tests/ui/panic_catch.rs");
+        t.pass("tests/ui/panic_abort.rs");
+        t.pass("tests/ui/registered_hooks.rs");
+        t.pass("tests/ui/named_args.rs");
+        t.pass("tests/ui/another_crate.rs");
+        t.pass("tests/ui/gated_method_name.rs");
+        t.compile_fail("tests/ui/invalid_panic_option.rs");
+        t.compile_fail("tests/ui/hook_too_new.rs");
+        t.compile_fail("tests/ui/hook_too_new_warn.rs");
+        t.compile_fail("tests/ui/invalid_named_args.rs");
+        t.compile_fail("tests/ui/missing_version.rs");
+        t.compile_fail("tests/ui/duplicate_version.rs");
+        t.compile_fail("tests/ui/duplicate_plugin.rs");
     }
 }
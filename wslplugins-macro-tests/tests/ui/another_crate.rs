@@ -0,0 +1,14 @@
+use wslplugins_rs::windows::core::Result;
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin;
+
+// Each case is a crate of its own, built in the same run as the other plugins
+#[wsl_plugin_v1(2, 0, 5)]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+        Ok(Plugin)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+use wslplugins_rs::windows::core::Result;
+use wslplugins_rs::*;
+
+mod first {
+    use super::*;
+
+    pub(crate) struct Plugin;
+
+    #[wsl_plugin_v1(2, 0, 5)]
+    impl<'a> WSLPluginV1<'a> for Plugin {
+        fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+            Ok(Plugin)
+        }
+    }
+}
+
+// A different entry point does not make a second plugin possible
+mod second {
+    use super::*;
+
+    pub(crate) struct Plugin;
+
+    #[wsl_plugin_v1(min_version = "2.0.5", entry_point = "SecondEntryPoint")]
+    impl<'a> WSLPluginV1<'a> for Plugin {
+        fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+            Ok(Plugin)
+        }
+    }
+}
+
+fn main() {}
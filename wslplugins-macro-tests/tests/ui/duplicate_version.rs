@@ -0,0 +1,12 @@
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin;
+
+#[wsl_plugin_v1(2, 0, 5, min_version = "2.1.2")]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> wslplugins_rs::windows::core::Result<Self> {
+        Ok(Plugin)
+    }
+}
+
+fn main() {}
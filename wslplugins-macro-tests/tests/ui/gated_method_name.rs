@@ -0,0 +1,23 @@
+use wslplugins_rs::windows::core::Result;
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin;
+
+// Shares its name with `WslCommand::in_distribution`
+fn in_distribution(session: &WSLSessionInformation) -> bool {
+    session.id() != 0
+}
+
+#[wsl_plugin_v1(2, 0, 5)]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+        Ok(Plugin)
+    }
+
+    fn on_vm_stopping(&self, session: &WSLSessionInformation) -> Result<()> {
+        in_distribution(session);
+        Ok(())
+    }
+}
+
+fn main() {}
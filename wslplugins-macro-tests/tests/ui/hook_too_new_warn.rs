@@ -0,0 +1,31 @@
+#![deny(deprecated)]
+
+use wslplugins_rs::windows::core::Result;
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin<'a> {
+    api: ApiV1<'a>,
+}
+
+#[wsl_plugin_v1(2, 0, 5, version_check = "warn")]
+impl<'a> WSLPluginV1<'a> for Plugin<'a> {
+    fn try_new(api: ApiV1<'a>) -> Result<Self> {
+        Ok(Plugin { api })
+    }
+
+    fn on_distribution_registered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &OfflineDistributionInformation,
+    ) -> Result<()> {
+        self.api.execute_binary_in_distribution(
+            session,
+            distribution.id(),
+            "/bin/true",
+            &["/bin/true"],
+        )?;
+        Ok(())
+    }
+}
+
+fn main() {}
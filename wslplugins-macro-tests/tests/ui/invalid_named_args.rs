@@ -0,0 +1,17 @@
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin;
+
+#[wsl_plugin_v1(
+    min_version = "2.x",
+    panic = "unwind",
+    colour = "blue",
+    entry_point = "plugin-entry",
+)]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> wslplugins_rs::windows::core::Result<Self> {
+        Ok(Plugin)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use wslplugins_rs::sys::{WSLPluginAPIV1, WSLPluginHooksV1, WSLVersion as NativeVersion};
+use wslplugins_rs::windows::core::Result;
+use wslplugins_rs::*;
+
+mod reexport {
+    pub use wslplugins_rs;
+}
+
+static LOGGER_READY: AtomicBool = AtomicBool::new(false);
+
+fn init_logger() -> Result<()> {
+    LOGGER_READY.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+pub(crate) struct Plugin;
+
+#[wsl_plugin_v1(
+    min_version = "2.0.5",
+    panic = "catch",
+    logger = init_logger,
+    entry_point = "PluginEntry",
+    crate = crate::reexport::wslplugins_rs,
+)]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+        assert!(LOGGER_READY.load(Ordering::SeqCst));
+        Ok(Plugin)
+    }
+
+    fn on_vm_stopping(&self, _session: &WSLSessionInformation) -> Result<()> {
+        Ok(())
+    }
+}
+
+// The logger is set up before the plugin is created
+fn main() {
+    let api: &'static WSLPluginAPIV1 = Box::leak(Box::new(WSLPluginAPIV1 {
+        Version: NativeVersion {
+            Major: 2,
+            Minor: 1,
+            Revision: 3,
+        },
+        MountFolder: None,
+        ExecuteBinary: None,
+        PluginError: None,
+        ExecuteBinaryInDistribution: None,
+    }));
+    let mut hooks: WSLPluginHooksV1 = unsafe { std::mem::zeroed() };
+    create_plugin(api, &mut hooks).unwrap();
+    assert!(LOGGER_READY.load(Ordering::SeqCst));
+    assert!(hooks.OnVMStopping.is_some());
+}
@@ -0,0 +1,18 @@
+use wslplugins_rs::windows::core::Result;
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin;
+
+#[wsl_plugin_v1(2, 0, 5, panic = "abort")]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+        Ok(Plugin)
+    }
+
+    fn on_vm_stopping(&self, _session: &WSLSessionInformation) -> Result<()> {
+        Ok(())
+    }
+}
+
+// trybuild need mains
+fn main() {}
@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wslplugins_rs::sys::{
+    WSLPluginAPIV1, WSLPluginHooksV1, WSLSessionInformation as NativeSession,
+    WSLVmCreationSettings as NativeSettings, WSLVersion as NativeVersion,
+};
+use wslplugins_rs::windows::core::{Result, HRESULT, PCWSTR};
+use wslplugins_rs::windows::Win32::Foundation::{E_UNEXPECTED, HANDLE, S_OK};
+use wslplugins_rs::windows::Win32::Security::PSID;
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin;
+
+#[wsl_plugin_v1(2, 0, 5)]
+impl<'a> WSLPluginV1<'a> for Plugin {
+    fn try_new(_api: ApiV1<'a>) -> Result<Self> {
+        Ok(Plugin)
+    }
+
+    fn on_vm_started(
+        &self,
+        _session: &WSLSessionInformation,
+        _user_settings: &WSLVmCreationSettings,
+    ) -> Result<()> {
+        panic!("boom")
+    }
+
+    fn on_vm_stopping(&self, _session: &WSLSessionInformation) -> Result<()> {
+        panic!("boom")
+    }
+}
+
+static PLUGIN_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" fn plugin_error(_message: PCWSTR) -> HRESULT {
+    PLUGIN_ERRORS.fetch_add(1, Ordering::SeqCst);
+    S_OK
+}
+
+// The generated functions are called as WSL would, the panics must not unwind out of them
+fn main() {
+    let api: &'static WSLPluginAPIV1 = Box::leak(Box::new(WSLPluginAPIV1 {
+        Version: NativeVersion {
+            Major: 2,
+            Minor: 1,
+            Revision: 3,
+        },
+        MountFolder: None,
+        ExecuteBinary: None,
+        PluginError: Some(plugin_error),
+        ExecuteBinaryInDistribution: None,
+    }));
+    let mut hooks: WSLPluginHooksV1 = unsafe { std::mem::zeroed() };
+    create_plugin(api, &mut hooks).unwrap();
+
+    let session = NativeSession {
+        SessionId: 1,
+        UserToken: HANDLE(std::ptr::null_mut()),
+        UserSid: PSID(std::ptr::null_mut()),
+    };
+    let settings = NativeSettings {
+        CustomConfigurationFlags: 0,
+    };
+    let code = unsafe { hooks.OnVMStarted.unwrap()(&session, &settings) };
+    assert_eq!(code, E_UNEXPECTED);
+    assert_eq!(PLUGIN_ERRORS.load(Ordering::SeqCst), 1);
+    // WSL does not take an error message from this hook
+    let code = unsafe { hooks.OnVMStopping.unwrap()(&session) };
+    assert_eq!(code, E_UNEXPECTED);
+    assert_eq!(PLUGIN_ERRORS.load(Ordering::SeqCst), 1);
+}
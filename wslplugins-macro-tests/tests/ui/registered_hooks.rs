@@ -0,0 +1,38 @@
+use wslplugins_rs::windows::core::Result;
+use wslplugins_rs::*;
+
+pub(crate) struct Plugin<'a> {
+    api: ApiV1<'a>,
+}
+
+#[wsl_plugin_v1(2, 1, 2)]
+impl<'a> WSLPluginV1<'a> for Plugin<'a> {
+    fn try_new(api: ApiV1<'a>) -> Result<Self> {
+        Ok(Plugin { api })
+    }
+
+    fn on_distribution_registered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &OfflineDistributionInformation,
+    ) -> Result<()> {
+        self.api.execute_binary_in_distribution(
+            session,
+            distribution.id(),
+            "/bin/true",
+            &["/bin/true"],
+        )?;
+        Ok(())
+    }
+
+    fn on_distribution_unregistered(
+        &self,
+        _session: &WSLSessionInformation,
+        _distribution: &OfflineDistributionInformation,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+// trybuild need mains
+fn main() {}
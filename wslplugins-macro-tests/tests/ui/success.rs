@@ -1,4 +1,4 @@
-use windows::core::Result;
+use wslplugins_rs::windows::core::Result;
 use wslplugins_rs::*;
 
 pub(crate) struct Plugin<'a> {
@@ -0,0 +1,385 @@
+//! JSON-RPC protocol spoken by `wslplugins-agent`, and the client driving it.
+//!
+//! The agent is launched in a distribution through
+//! [`ApiV1::execute_binary_in_distribution`](crate::ApiV1::execute_binary_in_distribution) and
+//! answers JSON-RPC 2.0 requests, one per [frame](crate::framing), until the plugin shuts down
+//! its side of the socket.
+use crate::framing::Channel;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Time given to a command run without [`RunCommand::timeout_ms`].
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Error codes defined by JSON-RPC 2.0.
+pub mod error_code {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// The operation failed in the distribution, e.g. a missing file.
+    pub const IO_ERROR: i64 = -32000;
+    /// The command did not exit in time, it was killed.
+    pub const TIMEOUT: i64 = -32001;
+}
+
+/// Methods answered by the agent with their parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum Method {
+    /// Answered with the content of the file as [`Bytes`].
+    ReadFile { path: String },
+    /// Answered with a list of [`ProcessInfo`].
+    ListProcesses,
+    /// Answered with the fields of `/etc/os-release`.
+    OsRelease,
+    /// Answered with a [`CommandOutput`].
+    RunCommand(RunCommand),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_dir: Option<String>,
+    /// The command and the processes it started are killed after this delay, `None` for
+    /// [`DEFAULT_COMMAND_TIMEOUT`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Bytes sent as a base64 string rather than an array of numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bytes(#[serde(with = "crate::serde_utils::base64")] pub Vec<u8>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+    pub id: u64,
+    #[serde(flatten)]
+    pub method: Method,
+}
+
+impl Request {
+    pub fn new(id: u64, method: Method) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_owned(),
+            id,
+            method,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+    /// `None` when the id of the request could not be read.
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+impl Response {
+    pub fn result(id: u64, result: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_owned(),
+            id: Some(id),
+            outcome: Outcome::Result(result),
+        }
+    }
+
+    pub fn error(id: Option<u64>, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_owned(),
+            id,
+            outcome: Outcome::Error(error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Result(serde_json::Value),
+    Error(RpcError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl Error for RpcError {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    /// Name of the executable, truncated to 15 bytes by the kernel.
+    pub name: String,
+    /// Empty for kernel threads and zombies.
+    pub cmdline: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandOutput {
+    /// `None` when the program was killed by a signal.
+    pub status: Option<i32>,
+    #[serde(with = "crate::serde_utils::base64")]
+    pub stdout: Vec<u8>,
+    #[serde(with = "crate::serde_utils::base64")]
+    pub stderr: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum AgentError {
+    Io(io::Error),
+    /// The agent answered with an error.
+    Rpc(RpcError),
+    /// The answer of the agent does not match the request.
+    InvalidResponse(String),
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentError::Io(err) => write!(f, "unable to reach the agent: {}", err),
+            AgentError::Rpc(err) => write!(f, "the agent failed: {}", err),
+            AgentError::InvalidResponse(message) => {
+                write!(f, "invalid response from the agent: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for AgentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AgentError::Io(err) => Some(err),
+            AgentError::Rpc(err) => Some(err),
+            AgentError::InvalidResponse(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for AgentError {
+    fn from(err: io::Error) -> Self {
+        AgentError::Io(err)
+    }
+}
+
+/// Typed client of `wslplugins-agent`, requests are answered in order.
+pub struct AgentClient<S = TcpStream> {
+    channel: Channel<Request, Response, S>,
+    next_id: u64,
+}
+
+impl<S: Read + Write> AgentClient<S> {
+    /// Client of the agent connected to `stream`.
+    pub fn new(stream: S) -> Self {
+        Self {
+            channel: Channel::new(stream),
+            next_id: 1,
+        }
+    }
+
+    /// Calls `method` and decodes its result.
+    pub fn call<T: DeserializeOwned>(&mut self, method: Method) -> Result<T, AgentError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let response = self.channel.request(&Request::new(id, method))?;
+        if response.id != Some(id) {
+            return Err(AgentError::InvalidResponse(format!(
+                "expected the response to {}, got {:?}",
+                id, response.id
+            )));
+        }
+        match response.outcome {
+            Outcome::Result(result) => serde_json::from_value(result)
+                .map_err(|err| AgentError::InvalidResponse(err.to_string())),
+            Outcome::Error(err) => Err(AgentError::Rpc(err)),
+        }
+    }
+
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>, AgentError> {
+        self.call(Method::ReadFile {
+            path: path.to_owned(),
+        })
+        .map(|Bytes(data)| data)
+    }
+
+    pub fn list_processes(&mut self) -> Result<Vec<ProcessInfo>, AgentError> {
+        self.call(Method::ListProcesses)
+    }
+
+    pub fn os_release(&mut self) -> Result<BTreeMap<String, String>, AgentError> {
+        self.call(Method::OsRelease)
+    }
+
+    pub fn run_command(&mut self, command: RunCommand) -> Result<CommandOutput, AgentError> {
+        self.call(Method::RunCommand(command))
+    }
+
+    pub fn into_inner(self) -> S {
+        self.channel.into_inner()
+    }
+}
+
+impl AgentClient<TcpStream> {
+    /// Launches the agent at `agent_path` in a user distribution.
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    pub fn launch(
+        api: &crate::ApiV1,
+        session: &crate::WSLSessionInformation,
+        distribution_id: &crate::windows::core::GUID,
+        agent_path: &str,
+    ) -> crate::windows::core::Result<Self> {
+        let stream = api.execute_binary_in_distribution(
+            session,
+            distribution_id,
+            agent_path,
+            &[agent_path],
+        )?;
+        Ok(Self::new(stream))
+    }
+
+    /// Lets the agent exit once it has answered.
+    pub fn shutdown(&self) -> io::Result<()> {
+        self.channel.shutdown()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_wire_format() {
+        let request = Request::new(
+            3,
+            Method::RunCommand(RunCommand {
+                program: "/bin/uname".into(),
+                args: vec!["-r".into()],
+                current_dir: None,
+                timeout_ms: None,
+            }),
+        );
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "run_command",
+                "params": {"program": "/bin/uname", "args": ["-r"]}
+            })
+        );
+        assert_eq!(serde_json::from_value::<Request>(value).unwrap(), request);
+        assert_eq!(
+            serde_json::from_value::<Request>(
+                json!({"jsonrpc": "2.0", "id": 4, "method": "list_processes"})
+            )
+            .unwrap(),
+            Request::new(4, Method::ListProcesses)
+        );
+    }
+
+    #[test]
+    fn test_response_wire_format() {
+        let value = serde_json::to_value(Response::error(
+            None,
+            RpcError {
+                code: error_code::PARSE_ERROR,
+                message: "parse error".into(),
+            },
+        ))
+        .unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": {"code": -32700, "message": "parse error"}
+            })
+        );
+        let response: Response =
+            serde_json::from_value(json!({"jsonrpc": "2.0", "id": 1, "result": [1, 2]})).unwrap();
+        assert_eq!(response, Response::result(1, json!([1, 2])));
+    }
+
+    #[test]
+    fn test_bytes_wire_format() {
+        let output = CommandOutput {
+            status: Some(0),
+            stdout: b"host\n".to_vec(),
+            stderr: vec![],
+        };
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            value,
+            json!({"status": 0, "stdout": "aG9zdAo=", "stderr": ""})
+        );
+        assert_eq!(
+            serde_json::from_value::<CommandOutput>(value).unwrap(),
+            output
+        );
+        assert_eq!(
+            serde_json::to_value(Bytes(vec![0, 255])).unwrap(),
+            json!("AP8=")
+        );
+        assert!(serde_json::from_value::<Bytes>(json!([104, 111])).is_err());
+        assert!(serde_json::from_value::<Bytes>(json!("not base64!")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_client() {
+        use crate::framing::serve;
+        use std::os::unix::net::UnixStream;
+        let (plugin, agent) = UnixStream::pair().unwrap();
+        let agent = std::thread::spawn(move || {
+            serve(agent, |request: Request| match request.method {
+                Method::ReadFile { path } if path == "/etc/hostname" => {
+                    Response::result(request.id, json!(Bytes(b"host\n".to_vec())))
+                }
+                _ => Response::error(
+                    Some(request.id),
+                    RpcError {
+                        code: error_code::IO_ERROR,
+                        message: "no such file".into(),
+                    },
+                ),
+            })
+        });
+        let mut client = AgentClient::new(plugin);
+        assert_eq!(client.read_file("/etc/hostname").unwrap(), b"host\n");
+        let err = client.read_file("/missing").unwrap_err();
+        assert!(matches!(
+            err,
+            AgentError::Rpc(RpcError { code: -32000, .. })
+        ));
+        drop(client);
+        agent.join().unwrap().unwrap();
+    }
+}
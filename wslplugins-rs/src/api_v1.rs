@@ -1,24 +1,27 @@
 extern crate wslplugins_sys;
+#[cfg(feature = "api-2-1-2")]
+use crate::windows::core::GUID;
+use crate::windows::Win32::Networking::WinSock::SOCKET as WinSocket;
+use crate::windows::{
+    core::{Result, PCSTR, PCWSTR},
+    Win32::Foundation::BOOL,
+};
 use crate::wsl_session_information::WSLSessionInformation;
 use crate::{
+    api_v1_capabilities::{ApiEntryPoint, ApiV1Capabilities, NotSupportedError},
+    exec_options::{self, ExecError, ExecOptions},
+    platform::tcp_stream_from_socket,
     utils::{cstring_from_str, encode_wide_null_terminated},
+    wsl_command::WslCommand,
     wsl_version::WSLVersion,
 };
 use log_instrument::instrument;
-use std::ffi::{CString, OsStr, OsString};
+use std::ffi::{CString, OsStr};
 use std::iter::once;
 use std::mem::MaybeUninit;
 use std::net::TcpStream;
-use std::os::windows::io::FromRawSocket;
-use std::os::windows::raw::SOCKET;
 use std::path::Path;
-use std::str::FromStr;
 use typed_path::Utf8UnixPath;
-use windows::Win32::Networking::WinSock::SOCKET as WinSocket;
-use windows::{
-    core::{Result, GUID, PCSTR, PCWSTR},
-    Win32::Foundation::BOOL,
-};
 pub struct ApiV1<'a>(&'a wslplugins_sys::WSLPluginAPIV1);
 
 impl<'a> From<&'a wslplugins_sys::WSLPluginAPIV1> for ApiV1<'a> {
@@ -29,9 +32,48 @@ impl<'a> From<&'a wslplugins_sys::WSLPluginAPIV1> for ApiV1<'a> {
 
 impl<'a> ApiV1<'a> {
     #[instrument]
-    pub fn version(&self) -> WSLVersion {
+    pub fn version(&self) -> WSLVersion<'_> {
         WSLVersion::from(&self.0.Version)
     }
+
+    /// Entry points provided by the running WSL, the other methods fail with a
+    /// [`NotSupportedError`] when called.
+    pub fn capabilities(&self) -> ApiV1Capabilities {
+        ApiV1Capabilities {
+            mount_folder: self
+                .entry_point(ApiEntryPoint::MountFolder, |api| api.MountFolder)
+                .is_ok(),
+            execute_binary: self
+                .entry_point(ApiEntryPoint::ExecuteBinary, |api| api.ExecuteBinary)
+                .is_ok(),
+            plugin_error: self
+                .entry_point(ApiEntryPoint::PluginError, |api| api.PluginError)
+                .is_ok(),
+            #[cfg(feature = "api-2-1-2")]
+            execute_binary_in_distribution: self
+                .entry_point(ApiEntryPoint::ExecuteBinaryInDistribution, |api| {
+                    api.ExecuteBinaryInDistribution
+                })
+                .is_ok(),
+        }
+    }
+
+    // The table handed out by an older WSL ends before the fields introduced later, so the version
+    // has to be checked before reading them
+    fn entry_point<F>(
+        &self,
+        entry_point: ApiEntryPoint,
+        get: impl FnOnce(&wslplugins_sys::WSLPluginAPIV1) -> Option<F>,
+    ) -> std::result::Result<F, NotSupportedError> {
+        let not_supported = NotSupportedError {
+            entry_point,
+            version: self.0.Version,
+        };
+        match entry_point.introduced_in() {
+            Some(introduced_in) if self.0.Version < introduced_in => Err(not_supported),
+            _ => get(self.0).ok_or(not_supported),
+        }
+    }
     /// Create plan9 mount between Windows & Linux
     #[instrument]
     pub fn mount_folder<WP: AsRef<Path>, UP: AsRef<Utf8UnixPath>>(
@@ -43,14 +85,12 @@ impl<'a> ApiV1<'a> {
         name: &OsStr,
     ) -> Result<()> {
         let encoded_windows_path = encode_wide_null_terminated(windows_path.as_ref().as_os_str());
-        let encoded_linux_path = encode_wide_null_terminated(
-            OsString::from_str(linux_path.as_ref().as_str())
-                .unwrap()
-                .as_os_str(),
-        );
+        let encoded_linux_path =
+            encode_wide_null_terminated(OsStr::new(linux_path.as_ref().as_str()));
         let encoded_name = encode_wide_null_terminated(name);
+        let mount_folder = self.entry_point(ApiEntryPoint::MountFolder, |api| api.MountFolder)?;
         let result = unsafe {
-            (*self.0).MountFolder.unwrap_unchecked()(
+            mount_folder(
                 session.id(),
                 PCWSTR::from_raw(encoded_windows_path.as_ptr()),
                 PCWSTR::from_raw(encoded_linux_path.as_ptr()),
@@ -84,9 +124,11 @@ impl<'a> ApiV1<'a> {
             .chain(Some(PCSTR::null()))
             .collect();
         let args_ptr = args_ptrs.as_mut_ptr();
+        let execute_binary =
+            self.entry_point(ApiEntryPoint::ExecuteBinary, |api| api.ExecuteBinary)?;
         let mut socket = MaybeUninit::<WinSocket>::uninit();
         let stream = unsafe {
-            (*self.0).ExecuteBinary.unwrap_unchecked()(
+            execute_binary(
                 session.id(),
                 PCSTR::from_raw(c_path.as_ptr()),
                 args_ptr,
@@ -94,21 +136,61 @@ impl<'a> ApiV1<'a> {
             )
             .ok()?;
             let socket = socket.assume_init();
-            TcpStream::from_raw_socket(socket.0 as SOCKET)
+            tcp_stream_from_socket(socket)
         };
         Ok(stream)
     }
 
+    /// Execute a program in the root namespace and read its output within the limits of `options`.
+    ///
+    /// On timeout the socket is shut down, the program gets EOF on its stdin and EPIPE on its
+    /// stdout.
+    pub fn execute_binary_with_options<P: AsRef<Utf8UnixPath>>(
+        &self,
+        session: &WSLSessionInformation,
+        path: P,
+        args: &[&str],
+        options: &ExecOptions,
+    ) -> std::result::Result<Vec<u8>, ExecError> {
+        let stream = self.execute_binary(session, path, args)?;
+        exec_options::read_to_end(stream, options)
+    }
+
+    /// Execute a program in the root namespace, the returned stream is registered with the
+    /// current tokio runtime, see [`run_with_deadline`](crate::run_with_deadline).
+    ///
+    /// # Panics
+    /// Panics when called outside of a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn execute_binary_async<P: AsRef<Utf8UnixPath>>(
+        &self,
+        session: &WSLSessionInformation,
+        path: P,
+        args: &[&str],
+    ) -> Result<tokio::net::TcpStream> {
+        let stream = self.execute_binary(session, path, args)?;
+        Ok(tokio_stream_from_std(stream)?)
+    }
+
+    /// Builder for a program to execute, see [`WslCommand`].
+    pub fn command<'c, P: AsRef<Utf8UnixPath>>(
+        &'c self,
+        session: &'c WSLSessionInformation<'c>,
+        program: P,
+    ) -> WslCommand<'c, 'a> {
+        WslCommand::new(self, session, program)
+    }
+
     /// Set the error message to display to the user if the VM or distribution creation fails.
     #[instrument]
     pub fn plugin_error(&self, error: &OsStr) -> Result<()> {
         let error_vec = encode_wide_null_terminated(error);
-        unsafe {
-            (*self.0).PluginError.unwrap_unchecked()(PCWSTR::from_raw(error_vec.as_ptr())).ok()
-        }
+        let plugin_error = self.entry_point(ApiEntryPoint::PluginError, |api| api.PluginError)?;
+        unsafe { plugin_error(PCWSTR::from_raw(error_vec.as_ptr())).ok() }
     }
     /// Execute a program in a user distribution
     /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
     #[instrument]
     pub fn execute_binary_in_distribution<P: AsRef<Utf8UnixPath>>(
         &self,
@@ -133,9 +215,13 @@ impl<'a> ApiV1<'a> {
             .chain(Some(PCSTR::null()))
             .collect();
         let args_ptr = args_ptrs.as_mut_ptr();
+        let execute_binary_in_distribution = self
+            .entry_point(ApiEntryPoint::ExecuteBinaryInDistribution, |api| {
+                api.ExecuteBinaryInDistribution
+            })?;
         let mut socket = MaybeUninit::<WinSocket>::uninit();
         let stream = unsafe {
-            self.0.ExecuteBinaryInDistribution.unwrap_unchecked()(
+            execute_binary_in_distribution(
                 session.id(),
                 distribution_id,
                 path_ptr,
@@ -144,8 +230,338 @@ impl<'a> ApiV1<'a> {
             )
             .ok()?;
             let socket = socket.assume_init();
-            TcpStream::from_raw_socket(socket.0 as SOCKET)
+            tcp_stream_from_socket(socket)
         };
         Ok(stream)
     }
+
+    /// Execute a program in a user distribution and read its output within the limits of
+    /// `options`, see [`ApiV1::execute_binary_with_options`].
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    pub fn execute_binary_in_distribution_with_options<P: AsRef<Utf8UnixPath>>(
+        &self,
+        session: &WSLSessionInformation,
+        distribution_id: &GUID,
+        path: P,
+        args: &[&str],
+        options: &ExecOptions,
+    ) -> std::result::Result<Vec<u8>, ExecError> {
+        let stream = self.execute_binary_in_distribution(session, distribution_id, path, args)?;
+        exec_options::read_to_end(stream, options)
+    }
+
+    /// Execute a program in a user distribution, the returned stream is registered with the
+    /// current tokio runtime, see [`ApiV1::execute_binary_async`].
+    /// Introduced in 2.1.2
+    ///
+    /// # Panics
+    /// Panics when called outside of a tokio runtime.
+    #[cfg(all(feature = "api-2-1-2", feature = "tokio"))]
+    pub fn execute_binary_in_distribution_async<P: AsRef<Utf8UnixPath>>(
+        &self,
+        session: &WSLSessionInformation,
+        distribution_id: &GUID,
+        path: P,
+        args: &[&str],
+    ) -> Result<tokio::net::TcpStream> {
+        let stream = self.execute_binary_in_distribution(session, distribution_id, path, args)?;
+        Ok(tokio_stream_from_std(stream)?)
+    }
+}
+
+// The socket is blocking when handed out by WSL
+#[cfg(feature = "tokio")]
+fn tokio_stream_from_std(stream: TcpStream) -> std::io::Result<tokio::net::TcpStream> {
+    stream.set_nonblocking(true)?;
+    tokio::net::TcpStream::from_std(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Call};
+    use crate::windows::core::HRESULT;
+    use crate::windows::Win32::Foundation::E_FAIL;
+    use crate::WslError;
+    use std::io::Read;
+
+    #[test]
+    fn test_version() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        assert_eq!(api.version().to_string(), "2.1.3");
+    }
+
+    #[test]
+    fn test_mount_folder() {
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        ApiV1::from(&api)
+            .mount_folder(
+                &WSLSessionInformation::from(&session),
+                "C:\\Users\\Public",
+                Utf8UnixPath::new("/mnt/public"),
+                true,
+                OsStr::new("public"),
+            )
+            .unwrap();
+        assert_eq!(
+            mock::calls(),
+            [Call::MountFolder {
+                session: 1,
+                windows_path: "C:\\Users\\Public".into(),
+                linux_path: "/mnt/public".into(),
+                read_only: true,
+                name: "public".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_execute_binary() {
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        mock::set_output(b"Linux version 5.15");
+        let mut stream = ApiV1::from(&api)
+            .execute_binary(
+                &WSLSessionInformation::from(&session),
+                "/bin/cat",
+                &["/bin/cat", "/proc/version"],
+            )
+            .unwrap();
+        let mut output = String::new();
+        stream.read_to_string(&mut output).unwrap();
+        assert_eq!(output, "Linux version 5.15");
+        assert_eq!(
+            mock::calls(),
+            [Call::ExecuteBinary {
+                session: 1,
+                path: "/bin/cat".into(),
+                args: vec!["/bin/cat".into(), "/proc/version".into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_execute_binary_error() {
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        mock::set_result(E_FAIL);
+        let result = ApiV1::from(&api).execute_binary(
+            &WSLSessionInformation::from(&session),
+            "/bin/ls",
+            &[],
+        );
+        assert_eq!(result.unwrap_err().code(), E_FAIL);
+    }
+
+    #[test]
+    fn test_execute_binary_with_options() {
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        mock::set_output(b"Linux version 5.15");
+        let output = ApiV1::from(&api)
+            .execute_binary_with_options(
+                &WSLSessionInformation::from(&session),
+                "/bin/cat",
+                &["/bin/cat", "/proc/version"],
+                &ExecOptions::default().with_timeout(std::time::Duration::from_secs(10)),
+            )
+            .unwrap();
+        assert_eq!(output, b"Linux version 5.15");
+    }
+
+    #[test]
+    fn test_execute_binary_with_options_timeout() {
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        // A program that never exits, until its stdin is closed
+        mock::set_peer(move |mut stream| {
+            let mut buffer = Vec::new();
+            sender
+                .send(stream.read_to_end(&mut buffer).is_ok())
+                .unwrap();
+        });
+        let err = ApiV1::from(&api)
+            .execute_binary_with_options(
+                &WSLSessionInformation::from(&session),
+                "/bin/sleep",
+                &["/bin/sleep", "infinity"],
+                &ExecOptions::default().with_timeout(std::time::Duration::from_millis(50)),
+            )
+            .unwrap_err();
+        assert!(matches!(err, ExecError::Timeout(_)));
+        assert!(receiver
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .unwrap());
+    }
+
+    #[test]
+    fn test_execute_binary_with_options_error() {
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        mock::set_result(E_FAIL);
+        let err = ApiV1::from(&api)
+            .execute_binary_with_options(
+                &WSLSessionInformation::from(&session),
+                "/bin/ls",
+                &[],
+                &ExecOptions::default(),
+            )
+            .unwrap_err();
+        assert_eq!(HRESULT::from(err), E_FAIL);
+    }
+
+    #[cfg(feature = "api-2-1-2")]
+    #[test]
+    fn test_execute_binary_in_distribution() {
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        let distribution = GUID::from_u128(0x6f2b_1c2e_4d3a_4b5c_8d9e_0f1a_2b3c_4d5e);
+        mock::set_output(b"ID=debian");
+        let mut stream = ApiV1::from(&api)
+            .execute_binary_in_distribution(
+                &WSLSessionInformation::from(&session),
+                &distribution,
+                "/bin/cat",
+                &["/bin/cat", "/etc/os-release"],
+            )
+            .unwrap();
+        let mut output = String::new();
+        stream.read_to_string(&mut output).unwrap();
+        assert_eq!(output, "ID=debian");
+        assert_eq!(
+            mock::calls(),
+            [Call::ExecuteBinaryInDistribution {
+                session: 1,
+                distribution,
+                path: "/bin/cat".into(),
+                args: vec!["/bin/cat".into(), "/etc/os-release".into()],
+            }]
+        );
+    }
+
+    #[cfg(all(feature = "api-2-1-2", feature = "tokio"))]
+    #[test]
+    fn test_execute_binary_in_distribution_async() {
+        use tokio::io::AsyncReadExt;
+        let api = mock::api(2, 1, 3);
+        let session = mock::session(1);
+        let distribution = GUID::from_u128(0x6f2b_1c2e_4d3a_4b5c_8d9e_0f1a_2b3c_4d5e);
+        mock::set_output(b"ID=debian");
+        let output = crate::run_with_deadline(std::time::Duration::from_secs(10), async {
+            let mut stream = ApiV1::from(&api)
+                .execute_binary_in_distribution_async(
+                    &WSLSessionInformation::from(&session),
+                    &distribution,
+                    "/bin/cat",
+                    &["/bin/cat", "/etc/os-release"],
+                )
+                .unwrap();
+            let mut output = String::new();
+            stream.read_to_string(&mut output).await.unwrap();
+            output
+        })
+        .unwrap();
+        assert_eq!(output, "ID=debian");
+        assert_eq!(mock::calls().len(), 1);
+    }
+
+    #[test]
+    fn test_plugin_error() {
+        let api = mock::api(2, 1, 3);
+        ApiV1::from(&api)
+            .plugin_error(OsStr::new("Something went wrong"))
+            .unwrap();
+        assert_eq!(
+            mock::calls(),
+            [Call::PluginError("Something went wrong".into())]
+        );
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let api = mock::api(2, 1, 3);
+        let capabilities = ApiV1::from(&api).capabilities();
+        assert!(capabilities.mount_folder);
+        assert!(capabilities.execute_binary);
+        assert!(capabilities.plugin_error);
+        #[cfg(feature = "api-2-1-2")]
+        assert!(capabilities.execute_binary_in_distribution);
+    }
+
+    #[test]
+    fn test_missing_entries() {
+        let mut api = mock::api(2, 1, 3);
+        api.MountFolder = None;
+        api.ExecuteBinary = None;
+        api.PluginError = None;
+        let session = mock::session(1);
+        let api = ApiV1::from(&api);
+        let capabilities = api.capabilities();
+        assert!(!capabilities.mount_folder);
+        assert!(!capabilities.execute_binary);
+        assert!(!capabilities.plugin_error);
+
+        let not_supported = WslError::EntryPointNotSupported.code();
+        let session = WSLSessionInformation::from(&session);
+        let result = api.mount_folder(
+            &session,
+            "C:\\",
+            Utf8UnixPath::new("/mnt/c"),
+            true,
+            OsStr::new("c"),
+        );
+        assert_eq!(result.unwrap_err().code(), not_supported);
+        let result = api.execute_binary(&session, "/bin/ls", &[]);
+        assert_eq!(result.unwrap_err().code(), not_supported);
+        let result = api.plugin_error(OsStr::new("Something went wrong"));
+        assert_eq!(result.unwrap_err().code(), not_supported);
+        assert_eq!(mock::calls(), []);
+    }
+
+    #[cfg(feature = "api-2-1-2")]
+    #[test]
+    fn test_execute_binary_in_distribution_missing() {
+        let mut api = mock::api(2, 1, 3);
+        api.ExecuteBinaryInDistribution = None;
+        let session = mock::session(1);
+        let api = ApiV1::from(&api);
+        assert!(!api.capabilities().execute_binary_in_distribution);
+        let err = api
+            .execute_binary_in_distribution(
+                &WSLSessionInformation::from(&session),
+                &GUID::zeroed(),
+                "/bin/ls",
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(WslError::from(err), WslError::EntryPointNotSupported);
+        assert_eq!(mock::calls(), []);
+    }
+
+    // The table of an older WSL ends before ExecuteBinaryInDistribution, whatever is there must
+    // not be called
+    #[cfg(feature = "api-2-1-2")]
+    #[test]
+    fn test_execute_binary_in_distribution_older_version() {
+        let api = mock::api(2, 0, 9);
+        let session = mock::session(1);
+        let api = ApiV1::from(&api);
+        let capabilities = api.capabilities();
+        assert!(capabilities.execute_binary);
+        assert!(!capabilities.execute_binary_in_distribution);
+        let err = api
+            .execute_binary_in_distribution(
+                &WSLSessionInformation::from(&session),
+                &GUID::zeroed(),
+                "/bin/ls",
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(WslError::from(err), WslError::EntryPointNotSupported);
+        assert_eq!(mock::calls(), []);
+    }
 }
@@ -0,0 +1,136 @@
+extern crate wslplugins_sys;
+use crate::windows::core::{Error as WinError, HRESULT};
+use crate::wsl_version::WSLVersion;
+use crate::WslError;
+use std::error::Error;
+use std::fmt;
+
+/// Entry points of the API table handed to the plugin by WSL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiEntryPoint {
+    MountFolder,
+    ExecuteBinary,
+    PluginError,
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    ExecuteBinaryInDistribution,
+}
+
+impl ApiEntryPoint {
+    /// First WSL version exposing the entry point, `None` if it is part of the original table.
+    pub fn introduced_in(self) -> Option<wslplugins_sys::WSLVersion> {
+        match self {
+            ApiEntryPoint::MountFolder
+            | ApiEntryPoint::ExecuteBinary
+            | ApiEntryPoint::PluginError => None,
+            #[cfg(feature = "api-2-1-2")]
+            ApiEntryPoint::ExecuteBinaryInDistribution => Some(wslplugins_sys::WSLVersion {
+                Major: 2,
+                Minor: 1,
+                Revision: 2,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ApiEntryPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Entry points of the API table the running WSL actually provides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ApiV1Capabilities {
+    pub mount_folder: bool,
+    pub execute_binary: bool,
+    pub plugin_error: bool,
+    #[cfg(feature = "api-2-1-2")]
+    pub execute_binary_in_distribution: bool,
+}
+
+impl ApiV1Capabilities {
+    pub fn supports(&self, entry_point: ApiEntryPoint) -> bool {
+        match entry_point {
+            ApiEntryPoint::MountFolder => self.mount_folder,
+            ApiEntryPoint::ExecuteBinary => self.execute_binary,
+            ApiEntryPoint::PluginError => self.plugin_error,
+            #[cfg(feature = "api-2-1-2")]
+            ApiEntryPoint::ExecuteBinaryInDistribution => self.execute_binary_in_distribution,
+        }
+    }
+}
+
+/// The entry point is missing from the API table of the running WSL.
+///
+/// Converted to [`WslError::EntryPointNotSupported`], a code of its own, so that it is not confused
+/// with `ERROR_NOT_SUPPORTED` returned by an entry point that does exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NotSupportedError {
+    pub entry_point: ApiEntryPoint,
+    pub version: wslplugins_sys::WSLVersion,
+}
+
+impl fmt::Display for NotSupportedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not supported by WSL {}",
+            self.entry_point,
+            WSLVersion::from(&self.version)
+        )
+    }
+}
+
+impl Error for NotSupportedError {}
+
+impl From<NotSupportedError> for HRESULT {
+    fn from(value: NotSupportedError) -> Self {
+        WslError::from(value).code()
+    }
+}
+
+impl From<NotSupportedError> for WinError {
+    fn from(value: NotSupportedError) -> Self {
+        WinError::new(HRESULT::from(value), value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supports() {
+        let capabilities = ApiV1Capabilities {
+            mount_folder: true,
+            execute_binary: false,
+            plugin_error: true,
+            #[cfg(feature = "api-2-1-2")]
+            execute_binary_in_distribution: false,
+        };
+        assert!(capabilities.supports(ApiEntryPoint::MountFolder));
+        assert!(!capabilities.supports(ApiEntryPoint::ExecuteBinary));
+        assert!(capabilities.supports(ApiEntryPoint::PluginError));
+        #[cfg(feature = "api-2-1-2")]
+        assert!(!capabilities.supports(ApiEntryPoint::ExecuteBinaryInDistribution));
+    }
+
+    #[test]
+    fn test_not_supported_error() {
+        let err = NotSupportedError {
+            entry_point: ApiEntryPoint::ExecuteBinary,
+            version: wslplugins_sys::WSLVersion {
+                Major: 2,
+                Minor: 0,
+                Revision: 9,
+            },
+        };
+        assert_eq!(
+            err.to_string(),
+            "ExecuteBinary is not supported by WSL 2.0.9"
+        );
+        assert_eq!(HRESULT::from(err), HRESULT(0xA0040001_u32 as i32));
+        assert_eq!(WinError::from(err).code(), HRESULT::from(err));
+    }
+}
@@ -0,0 +1,108 @@
+//! Asynchronous execution, behind the `tokio` feature.
+//!
+//! Hooks are synchronous, [`run_with_deadline`] drives the futures of a hook to completion, e.g.
+//! several probes started with [`ApiV1::execute_binary_async`](crate::ApiV1::execute_binary_async)
+//! and awaited together.
+use crate::ExecError;
+use std::future::Future;
+use std::time::Duration;
+
+/// Runs `future` to completion on a new current thread runtime, giving up after `timeout`.
+///
+/// On timeout the future is dropped, closing the streams it owns, so the programs it was
+/// waiting for get EOF on their stdin and EPIPE on their stdout.
+///
+/// # Panics
+/// Panics when called from within a tokio runtime, hooks are called on threads of WSL.
+pub fn run_with_deadline<F: Future>(timeout: Duration, future: F) -> Result<F::Output, ExecError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime
+        .block_on(async { tokio::time::timeout(timeout, future).await })
+        .map_err(|_| {
+            log::warn!("The hook did not complete within {:?}", timeout);
+            ExecError::Timeout(timeout)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use crate::{ApiV1, WSLSessionInformation};
+    use std::io::{Read, Write};
+    use std::sync::mpsc;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn test_run_with_deadline() {
+        assert_eq!(
+            run_with_deadline(Duration::from_secs(10), async { 42 }).unwrap(),
+            42
+        );
+    }
+
+    #[test]
+    fn test_concurrent_probes() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let (sender, receiver) = mpsc::channel();
+        // Each probe only completes once the other one has started
+        let peer = |name: &'static str, sender: mpsc::Sender<()>, other: mpsc::Receiver<()>| {
+            move |mut stream: std::net::TcpStream| {
+                sender.send(()).unwrap();
+                other.recv().unwrap();
+                stream.write_all(name.as_bytes()).unwrap();
+            }
+        };
+        let (other_sender, other_receiver) = mpsc::channel();
+        let outputs = run_with_deadline(Duration::from_secs(10), async {
+            mock::set_peer(peer("uname", other_sender, receiver));
+            let mut first = api
+                .execute_binary_async(&session, "/bin/uname", &["/bin/uname"])
+                .unwrap();
+            mock::set_peer(peer("hostname", sender, other_receiver));
+            let mut second = api
+                .execute_binary_async(&session, "/bin/hostname", &["/bin/hostname"])
+                .unwrap();
+            let (mut first_output, mut second_output) = (String::new(), String::new());
+            let (first_read, second_read) = tokio::join!(
+                first.read_to_string(&mut first_output),
+                second.read_to_string(&mut second_output)
+            );
+            first_read.unwrap();
+            second_read.unwrap();
+            (first_output, second_output)
+        })
+        .unwrap();
+        assert_eq!(outputs, ("uname".to_owned(), "hostname".to_owned()));
+    }
+
+    #[test]
+    fn test_deadline_elapsed() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let (sender, receiver) = mpsc::channel();
+        mock::set_peer(move |mut stream| {
+            let mut buffer = Vec::new();
+            sender
+                .send(stream.read_to_end(&mut buffer).is_ok())
+                .unwrap();
+        });
+        let err = run_with_deadline(Duration::from_millis(50), async {
+            let mut stream = api
+                .execute_binary_async(&session, "/bin/sleep", &["/bin/sleep", "infinity"])
+                .unwrap();
+            let mut buffer = Vec::new();
+            stream.read_to_end(&mut buffer).await
+        })
+        .unwrap_err();
+        assert!(matches!(err, ExecError::Timeout(_)));
+        assert!(receiver.recv_timeout(Duration::from_secs(10)).unwrap());
+    }
+}
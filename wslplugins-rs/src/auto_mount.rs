@@ -0,0 +1,401 @@
+//! Folders to mount whenever a VM starts, read from a TOML or JSON file.
+//!
+//! ```toml
+//! on_error = "abort"
+//!
+//! [[mounts]]
+//! windows_path = 'C:\Users\Public'
+//! linux_path = "/mnt/public"
+//! read_only = true
+//! name = "public"
+//!
+//! [[mounts]]
+//! windows_path = 'D:\Projects'
+//! linux_path = "/mnt/projects"
+//! name = "projects"
+//! # Only for this user
+//! user_sid = "S-1-5-21-1004336348-1177238915-682003330-1001"
+//! ```
+use crate::windows::core::{Error as WinError, Result};
+use crate::{ApiV1, MountError, MountRegistry, MountSpec, WSLSessionInformation};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What a failed entry does to the start of the VM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnMountError {
+    /// Log the failure and start the VM anyway.
+    #[default]
+    Continue,
+    /// Fail the start of the VM, the failures are shown to the user.
+    Abort,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AutoMountEntry {
+    pub windows_path: PathBuf,
+    pub linux_path: String,
+    #[serde(default)]
+    pub read_only: bool,
+    pub name: String,
+    /// Only mount for the sessions of this user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_sid: Option<String>,
+}
+
+impl AutoMountEntry {
+    fn applies_to(&self, session: &WSLSessionInformation) -> bool {
+        match &self.user_sid {
+            None => true,
+            Some(user_sid) => session
+                .user_sid_string()
+                .is_some_and(|sid| sid.eq_ignore_ascii_case(user_sid)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AutoMountConfig {
+    #[serde(default)]
+    pub on_error: OnMountError,
+    #[serde(default)]
+    pub mounts: Vec<AutoMountEntry>,
+}
+
+impl AutoMountConfig {
+    pub fn from_toml_str(config: &str) -> std::result::Result<Self, AutoMountConfigError> {
+        toml::from_str(config).map_err(AutoMountConfigError::Toml)
+    }
+
+    pub fn from_json_str(config: &str) -> std::result::Result<Self, AutoMountConfigError> {
+        serde_json::from_str(config).map_err(AutoMountConfigError::Json)
+    }
+
+    /// Reads a `.toml` or `.json` file.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> std::result::Result<Self, AutoMountConfigError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+        let parse = match extension.as_deref() {
+            Some("toml") => Self::from_toml_str,
+            Some("json") => Self::from_json_str,
+            _ => return Err(AutoMountConfigError::UnknownFormat(path.to_path_buf())),
+        };
+        parse(&fs::read_to_string(path).map_err(AutoMountConfigError::Io)?)
+    }
+}
+
+#[derive(Debug)]
+pub enum AutoMountConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    /// The extension is neither `.toml` nor `.json`.
+    UnknownFormat(PathBuf),
+}
+
+impl fmt::Display for AutoMountConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoMountConfigError::Io(err) => write!(f, "unable to read the mounts: {}", err),
+            AutoMountConfigError::Toml(err) => write!(f, "invalid mounts: {}", err),
+            AutoMountConfigError::Json(err) => write!(f, "invalid mounts: {}", err),
+            AutoMountConfigError::UnknownFormat(path) => {
+                write!(f, "{} is neither a TOML nor a JSON file", path.display())
+            }
+        }
+    }
+}
+
+impl Error for AutoMountConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AutoMountConfigError::Io(err) => Some(err),
+            AutoMountConfigError::Toml(err) => Some(err),
+            AutoMountConfigError::Json(err) => Some(err),
+            AutoMountConfigError::UnknownFormat(_) => None,
+        }
+    }
+}
+
+/// Applies an [`AutoMountConfig`] from the hooks of a plugin.
+#[derive(Debug, Default)]
+pub struct AutoMount {
+    config: AutoMountConfig,
+    registry: MountRegistry,
+}
+
+impl AutoMount {
+    pub fn new(config: AutoMountConfig) -> Self {
+        Self {
+            config,
+            registry: MountRegistry::new(),
+        }
+    }
+
+    pub fn config(&self) -> &AutoMountConfig {
+        &self.config
+    }
+
+    /// Mounts made so far, see [`MountRegistry`].
+    pub fn registry(&self) -> &MountRegistry {
+        &self.registry
+    }
+
+    /// To be called from [`WSLPluginV1::on_vm_started`](crate::WSLPluginV1::on_vm_started).
+    ///
+    /// Every entry is tried, the failures are logged. With [`OnMountError::Abort`] they are also
+    /// reported together through [`ApiV1::plugin_error`] and the error of the first one is
+    /// returned, failing the start of the VM. WSL rejects a message from a hook which succeeds,
+    /// so nothing is reported otherwise.
+    pub fn on_vm_started(&self, api: &ApiV1, session: &WSLSessionInformation) -> Result<()> {
+        let mut failures: Vec<(String, WinError)> = Vec::new();
+        for entry in &self.config.mounts {
+            if !entry.applies_to(session) {
+                log::debug!("Skipping the mount {} for this user", entry.name);
+                continue;
+            }
+            let result = match MountSpec::new(
+                &entry.windows_path,
+                entry.linux_path.as_str(),
+                entry.read_only,
+                &entry.name,
+            ) {
+                Ok(spec) => self
+                    .registry
+                    .mount(api, session, spec)
+                    .map_err(|err| match err {
+                        MountError::Wsl(err) => (err.to_string(), err),
+                        err => (err.to_string(), err.into()),
+                    }),
+                Err(err) => Err((err.to_string(), err.into())),
+            };
+            if let Err((reason, err)) = result {
+                let message = format!(
+                    "unable to mount {} on {}: {}",
+                    entry.windows_path.display(),
+                    entry.linux_path,
+                    reason
+                );
+                log::error!("{}", message);
+                failures.push((message, err));
+            }
+        }
+        match (self.config.on_error, failures.first()) {
+            (OnMountError::Abort, Some((_, err))) => {
+                let message = failures
+                    .iter()
+                    .map(|(message, _)| message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if let Err(err) = api.plugin_error(OsStr::new(&message)) {
+                    log::warn!("Unable to report the mount failures: {}", err);
+                }
+                Err(err.clone())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// To be called from [`WSLPluginV1::on_vm_stopping`](crate::WSLPluginV1::on_vm_stopping).
+    pub fn on_vm_stopping(&self, session: &WSLSessionInformation) {
+        self.registry.remove_session(session.id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Call};
+    use crate::windows::Win32::Foundation::E_FAIL;
+    use crate::WslError;
+
+    const CONFIG: &str = r#"
+on_error = "abort"
+
+[[mounts]]
+windows_path = 'C:\Users\Public'
+linux_path = "/mnt/public"
+read_only = true
+name = "public"
+
+[[mounts]]
+windows_path = 'D:\Projects'
+linux_path = "/mnt/projects"
+name = "projects"
+user_sid = "S-1-5-21-1-2-3-1001"
+"#;
+
+    fn entry(linux_path: &str, name: &str) -> AutoMountEntry {
+        AutoMountEntry {
+            windows_path: "C:\\Data".into(),
+            linux_path: linux_path.into(),
+            read_only: false,
+            name: name.into(),
+            user_sid: None,
+        }
+    }
+
+    #[test]
+    fn test_from_toml_str() {
+        let config = AutoMountConfig::from_toml_str(CONFIG).unwrap();
+        assert_eq!(config.on_error, OnMountError::Abort);
+        assert_eq!(
+            config.mounts,
+            [
+                AutoMountEntry {
+                    windows_path: "C:\\Users\\Public".into(),
+                    linux_path: "/mnt/public".into(),
+                    read_only: true,
+                    name: "public".into(),
+                    user_sid: None,
+                },
+                AutoMountEntry {
+                    windows_path: "D:\\Projects".into(),
+                    linux_path: "/mnt/projects".into(),
+                    read_only: false,
+                    name: "projects".into(),
+                    user_sid: Some("S-1-5-21-1-2-3-1001".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_json_str() {
+        let config = AutoMountConfig::from_json_str(
+            r#"{"mounts": [{"windows_path": "C:\\Data", "linux_path": "/mnt/data", "name": "data"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.on_error, OnMountError::Continue);
+        assert_eq!(config.mounts, [entry("/mnt/data", "data")]);
+        assert!(matches!(
+            AutoMountConfig::from_json_str(r#"{"mounts": [{}]}"#),
+            Err(AutoMountConfigError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_path() {
+        let dir =
+            std::env::temp_dir().join(format!("wslplugins-auto-mount-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mounts.toml"), CONFIG).unwrap();
+        fs::write(dir.join("mounts.json"), r#"{"on_error": "abort"}"#).unwrap();
+        let toml = AutoMountConfig::from_path(dir.join("mounts.toml"));
+        let json = AutoMountConfig::from_path(dir.join("mounts.json"));
+        let missing = AutoMountConfig::from_path(dir.join("missing.toml"));
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(toml.unwrap().mounts.len(), 2);
+        assert_eq!(json.unwrap().on_error, OnMountError::Abort);
+        assert!(matches!(missing, Err(AutoMountConfigError::Io(_))));
+        assert!(matches!(
+            AutoMountConfig::from_path("mounts.yaml"),
+            Err(AutoMountConfigError::UnknownFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_user_condition() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let auto_mount = AutoMount::new(AutoMountConfig::from_toml_str(CONFIG).unwrap());
+
+        let other_sid = mock::sid(&[1, 2, 3, 1002]);
+        let session = mock::session_with_sid(1, &other_sid);
+        auto_mount
+            .on_vm_started(&api, &WSLSessionInformation::from(&session))
+            .unwrap();
+        assert_eq!(mock::calls().len(), 1);
+
+        let sid = mock::sid(&[1, 2, 3, 1001]);
+        let session = mock::session_with_sid(2, &sid);
+        auto_mount
+            .on_vm_started(&api, &WSLSessionInformation::from(&session))
+            .unwrap();
+        assert_eq!(mock::calls().len(), 3);
+        let Call::MountFolder { linux_path, .. } = &mock::calls()[2] else {
+            panic!("mount_folder was not called");
+        };
+        assert_eq!(linux_path, "/mnt/projects");
+        assert_eq!(auto_mount.registry().mounts(2).len(), 2);
+
+        auto_mount.on_vm_stopping(&WSLSessionInformation::from(&session));
+        assert!(auto_mount.registry().mounts(2).is_empty());
+    }
+
+    #[test]
+    fn test_continue_on_error() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let auto_mount = AutoMount::new(AutoMountConfig {
+            on_error: OnMountError::Continue,
+            mounts: vec![
+                entry("mnt/relative", "relative"),
+                entry("/mnt/data", "data"),
+                entry("/mnt/data", "again"),
+            ],
+        });
+        auto_mount
+            .on_vm_started(&api, &WSLSessionInformation::from(&session))
+            .unwrap();
+        // Only the valid entry reaches WSL, nothing is reported
+        assert_eq!(mock::calls().len(), 1);
+        assert_eq!(auto_mount.registry().mounts(1).len(), 1);
+    }
+
+    #[test]
+    fn test_abort_on_error() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let auto_mount = AutoMount::new(AutoMountConfig {
+            on_error: OnMountError::Abort,
+            mounts: vec![entry("/mnt/data", "bad name"), entry("/mnt/other", "other")],
+        });
+        let err = auto_mount
+            .on_vm_started(&api, &WSLSessionInformation::from(&session))
+            .unwrap_err();
+        assert_eq!(WslError::from(err), WslError::InvalidArgument);
+        let calls = mock::calls();
+        assert_eq!(calls.len(), 2);
+        assert!(matches!(calls[0], Call::MountFolder { .. }));
+        assert_eq!(
+            calls[1],
+            Call::PluginError(
+                "unable to mount C:\\Data on /mnt/data: invalid mount name \"bad name\"".into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_abort_collects_every_failure() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let auto_mount = AutoMount::new(AutoMountConfig {
+            on_error: OnMountError::Abort,
+            mounts: vec![entry("/mnt/a", "a"), entry("/mnt/b", "b")],
+        });
+        mock::set_result(E_FAIL);
+        let err = auto_mount
+            .on_vm_started(&api, &WSLSessionInformation::from(&session))
+            .unwrap_err();
+        assert_eq!(err.code(), E_FAIL);
+        let Call::PluginError(message) = mock::calls().pop().unwrap() else {
+            panic!("plugin_error was not called");
+        };
+        let message = message.into_string().unwrap();
+        assert_eq!(message.lines().count(), 2);
+        assert!(message.contains("/mnt/a") && message.contains("/mnt/b"));
+    }
+}
@@ -1,9 +1,9 @@
+use crate::windows::core::GUID;
 use std::{
     ffi::OsString,
     fmt::{Debug, Display},
     hash,
 };
-use windows::core::GUID;
 
 pub trait CoreDistributionInformation {
     /// Distribution ID, guaranteed to be the same accross reboots
@@ -1,7 +1,8 @@
 extern crate wslplugins_sys;
 use crate::core_distribution_information::CoreDistributionInformation;
-use std::{ffi::OsString, os::windows::ffi::OsStringExt};
-use windows::core::GUID;
+use crate::platform::os_string_from_wide;
+use crate::windows::core::GUID;
+use std::ffi::OsString;
 
 pub struct DistributionInformation<'a>(&'a wslplugins_sys::WSLDistributionInformation);
 
@@ -28,7 +29,7 @@ impl CoreDistributionInformation for DistributionInformation<'_> {
     }
 
     fn name(&self) -> OsString {
-        unsafe { OsString::from_wide(self.0.Name.as_wide()) }
+        unsafe { os_string_from_wide(self.0.Name.as_wide()) }
     }
 
     fn package_family_name(&self) -> Option<OsString> {
@@ -37,8 +38,16 @@ impl CoreDistributionInformation for DistributionInformation<'_> {
             if ptr.is_null() {
                 None
             } else {
-                Some(OsString::from_wide(ptr.as_wide()))
+                Some(os_string_from_wide(ptr.as_wide()))
             }
         }
     }
 }
+
+// Same representation as the owned snapshot, so it can be read back as one
+#[cfg(feature = "serde")]
+impl serde::Serialize for DistributionInformation<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::OwnedDistributionInfo::from(self).serialize(serializer)
+    }
+}
@@ -0,0 +1,877 @@
+#[cfg(feature = "api-2-1-2")]
+use crate::offline_distribution_information::OfflineDistributionInformation;
+#[cfg(feature = "api-2-1-2")]
+use crate::owned_offline_distribution_info::OwnedOfflineDistributionInfo;
+use crate::windows::core::{Error as WinError, Result};
+use crate::wsl_user_configuration::WSLUserConfiguration;
+use crate::{
+    ApiV1, DistributionInformation, OwnedDistributionInfo, OwnedSessionInfo, WSLPluginV1,
+    WSLSessionInformation, WSLVmCreationSettings, WslError,
+};
+use std::collections::{HashSet, VecDeque};
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::time::Duration;
+
+/// What a hook does when the channel of an [`EventChannel`] is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BackPressure {
+    /// Waits for the worker to make room, which delays WSL.
+    #[default]
+    Block,
+    /// Discards the oldest queued event to make room.
+    DropOldest,
+    /// Fails the hook with [`WslError::Busy`], the event is lost.
+    FailHook,
+}
+
+/// Owned copy of a hook call, handed to the [`EventWorker`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum WslEvent {
+    VmStarted {
+        session: OwnedSessionInfo,
+        custom_configuration_flags: WSLUserConfiguration,
+    },
+    VmStopping {
+        session: OwnedSessionInfo,
+    },
+    DistributionStarted {
+        session: OwnedSessionInfo,
+        distribution: OwnedDistributionInfo,
+    },
+    DistributionStopping {
+        session: OwnedSessionInfo,
+        distribution: OwnedDistributionInfo,
+    },
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    DistributionRegistered {
+        session: OwnedSessionInfo,
+        distribution: OwnedOfflineDistributionInfo,
+    },
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    DistributionUnregistered {
+        session: OwnedSessionInfo,
+        distribution: OwnedOfflineDistributionInfo,
+    },
+}
+
+impl WslEvent {
+    /// Session the hook was called for.
+    pub fn session(&self) -> OwnedSessionInfo {
+        match self {
+            WslEvent::VmStarted { session, .. }
+            | WslEvent::VmStopping { session }
+            | WslEvent::DistributionStarted { session, .. }
+            | WslEvent::DistributionStopping { session, .. } => *session,
+            #[cfg(feature = "api-2-1-2")]
+            WslEvent::DistributionRegistered { session, .. }
+            | WslEvent::DistributionUnregistered { session, .. } => *session,
+        }
+    }
+}
+
+/// Work done on the thread of an [`EventChannel`], away from the hooks.
+pub trait EventWorker: Send + 'static {
+    /// Number of events waiting for the worker before [`Self::BACK_PRESSURE`] applies.
+    const CAPACITY: usize = 64;
+    const BACK_PRESSURE: BackPressure = BackPressure::Block;
+    /// Longest time `on_vm_stopping` waits for the queued events to be handled once the last VM
+    /// stopped. Past it, the hook returns and the thread ends on its own once done.
+    const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Called when WSL loads the plugin, before the worker thread is started.
+    fn try_new(api: ApiV1<'static>) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Called for each event, in the order of the hooks.
+    ///
+    /// The hooks do not wait for it, except `on_vm_stopping` for the last VM: it waits, up to
+    /// [`Self::STOP_TIMEOUT`], for every queued event to be handled. A slow `handle` delays the
+    /// shutdown of WSL by as much.
+    fn handle(&mut self, event: WslEvent);
+
+    /// Called once the last VM has stopped and the queued events are handled, before the thread
+    /// ends. A new thread is started for the next event.
+    fn on_idle(&mut self) {}
+
+    /// Called once the channel is closed and the queued events are handled.
+    fn on_shutdown(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Closed {
+    // Until the next event restarts the worker
+    Idle,
+    Shutdown,
+}
+
+#[derive(Default)]
+struct QueueState {
+    events: VecDeque<WslEvent>,
+    closed: Option<Closed>,
+    dropped: u64,
+    // Whether a worker thread is still busy with the queue
+    running: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    not_empty: Condvar,
+    not_full: Condvar,
+    stopped: Condvar,
+}
+
+impl Queue {
+    // A shut down queue stays so
+    fn close(&self, reason: Closed) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed != Some(Closed::Shutdown) {
+            state.closed = Some(reason);
+        }
+        drop(state);
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+
+    fn reopen(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed == Some(Closed::Idle) {
+            state.closed = None;
+        }
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.state.lock().unwrap().closed == Some(Closed::Shutdown)
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state.lock().unwrap().closed == Some(Closed::Idle)
+    }
+
+    // Whether the worker thread is done within `timeout`
+    fn wait_stopped(&self, timeout: Duration) -> bool {
+        let (state, _) = self
+            .stopped
+            .wait_timeout_while(self.state.lock().unwrap(), timeout, |state| state.running)
+            .unwrap();
+        !state.running
+    }
+
+    // `None` once the channel is closed and drained
+    fn pop(&self) -> Option<WslEvent> {
+        let mut state = self
+            .not_empty
+            .wait_while(self.state.lock().unwrap(), |state| {
+                state.events.is_empty() && state.closed.is_none()
+            })
+            .unwrap();
+        let event = state.events.pop_front();
+        drop(state);
+        self.not_full.notify_one();
+        event
+    }
+}
+
+// Tells `go_idle` the worker is done, and closes the channel when the worker panics, so hooks
+// stop waiting for it
+struct WorkerGuard(Arc<Queue>);
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.close(Closed::Shutdown);
+        }
+        self.0.state.lock().unwrap().running = false;
+        self.0.stopped.notify_all();
+    }
+}
+
+enum WorkerState<W> {
+    // Along with how the queue was closed when the worker returned
+    Running(JoinHandle<(W, Option<Closed>)>),
+    Idle(W),
+    Stopped,
+}
+
+/// [`WSLPluginV1`] sending each hook as a [`WslEvent`] over a bounded channel to an
+/// [`EventWorker`] running on its own thread, so slow work does not hold WSL back.
+///
+/// The thread is joined from `on_vm_stopping` once the last VM has stopped, after the queued
+/// events are handled, and started again by the next hook. The hook waits for the events up to
+/// [`EventWorker::STOP_TIMEOUT`]; past it, the thread ends on its own and the next hook waits for
+/// it. WSL stops the VMs before unloading the plugin, so no thread is left when the DLL is
+/// unloaded, as long as the worker is done within the timeout, even though the plugin kept by
+/// [`wsl_plugin_v1`](crate::wsl_plugin_v1) in a static is never dropped. Joining a thread from
+/// `DllMain` would deadlock on the loader lock, so neither [`shutdown`](Self::shutdown) nor a
+/// drop of the channel may happen there.
+pub struct EventChannel<W: EventWorker> {
+    queue: Arc<Queue>,
+    capacity: usize,
+    back_pressure: BackPressure,
+    stop_timeout: Duration,
+    worker: Mutex<WorkerState<W>>,
+    worker_thread: Mutex<Option<ThreadId>>,
+    // Sessions whose VM is running, the worker goes idle when the last one stops
+    sessions: Mutex<HashSet<u32>>,
+}
+
+// Runs `worker` until the queue is closed, then gives it back
+fn start<W: EventWorker>(
+    queue: Arc<Queue>,
+    mut worker: W,
+) -> io::Result<JoinHandle<(W, Option<Closed>)>> {
+    queue.state.lock().unwrap().running = true;
+    let spawned = thread::Builder::new()
+        .name("wsl-event-worker".into())
+        .spawn({
+            let queue = queue.clone();
+            move || {
+                let guard = WorkerGuard(queue);
+                while let Some(event) = guard.0.pop() {
+                    worker.handle(event);
+                }
+                let closed = guard.0.state.lock().unwrap().closed;
+                match closed {
+                    Some(Closed::Idle) => worker.on_idle(),
+                    Some(Closed::Shutdown) | None => worker.on_shutdown(),
+                }
+                (worker, closed)
+            }
+        });
+    if spawned.is_err() {
+        queue.state.lock().unwrap().running = false;
+    }
+    spawned
+}
+
+// Waits for the worker, unless called from it: joining itself would never return
+fn join<T>(handle: JoinHandle<T>) -> Option<T> {
+    if handle.thread().id() == thread::current().id() {
+        return None;
+    }
+    match handle.join() {
+        Ok(worker) => Some(worker),
+        Err(_) => {
+            log::error!("The event worker panicked");
+            None
+        }
+    }
+}
+
+impl<W: EventWorker> EventChannel<W> {
+    /// Starts `worker` with its [`CAPACITY`](EventWorker::CAPACITY) and
+    /// [`BACK_PRESSURE`](EventWorker::BACK_PRESSURE).
+    pub fn spawn(worker: W) -> io::Result<Self> {
+        Self::with_config(worker, W::CAPACITY, W::BACK_PRESSURE)
+    }
+
+    /// # Panics
+    /// If `capacity` is 0.
+    pub fn with_config(
+        worker: W,
+        capacity: usize,
+        back_pressure: BackPressure,
+    ) -> io::Result<Self> {
+        assert!(capacity > 0, "the capacity of the channel must not be 0");
+        let queue = Arc::new(Queue::default());
+        let handle = start(queue.clone(), worker)?;
+        Ok(Self {
+            queue,
+            capacity,
+            back_pressure,
+            stop_timeout: W::STOP_TIMEOUT,
+            worker_thread: Mutex::new(Some(handle.thread().id())),
+            worker: Mutex::new(WorkerState::Running(handle)),
+            sessions: Mutex::default(),
+        })
+    }
+
+    /// Queues `event` for the worker, applying the back-pressure policy when the channel is full.
+    /// The worker thread is started again if it went idle.
+    ///
+    /// Fails with [`WslError::Aborted`] once the channel is shut down.
+    pub fn send(&self, event: WslEvent) -> Result<()> {
+        let mut event = event;
+        loop {
+            self.wake()?;
+            match self.push(event)? {
+                None => return Ok(()),
+                // Went idle meanwhile, `wake` waits for it to be done
+                Some(rejected) => event = rejected,
+            }
+        }
+    }
+
+    fn wake(&self) -> Result<()> {
+        let mut worker = self.worker.lock().unwrap();
+        match std::mem::replace(&mut *worker, WorkerState::Stopped) {
+            WorkerState::Idle(idle) => self.restart(&mut worker, idle)?,
+            // Still busy past the timeout of `go_idle`, its thread is about to end
+            WorkerState::Running(handle) if self.queue.is_idle() => match join(handle) {
+                Some((idle, Some(Closed::Idle))) if !self.queue.is_shut_down() => {
+                    self.restart(&mut worker, idle)?
+                }
+                _ => self.queue.close(Closed::Shutdown),
+            },
+            state => *worker = state,
+        }
+        Ok(())
+    }
+
+    fn restart(&self, worker: &mut WorkerState<W>, idle: W) -> Result<()> {
+        self.queue.reopen();
+        let handle = start(self.queue.clone(), idle)?;
+        *self.worker_thread.lock().unwrap() = Some(handle.thread().id());
+        *worker = WorkerState::Running(handle);
+        Ok(())
+    }
+
+    // Gives `event` back if the worker went idle before it was queued
+    fn push(&self, event: WslEvent) -> Result<Option<WslEvent>> {
+        let mut state = self.queue.state.lock().unwrap();
+        if self.back_pressure == BackPressure::Block {
+            state = self
+                .queue
+                .not_full
+                .wait_while(state, |state| {
+                    state.events.len() >= self.capacity && state.closed.is_none()
+                })
+                .unwrap();
+        }
+        match state.closed {
+            Some(Closed::Idle) => return Ok(Some(event)),
+            Some(Closed::Shutdown) => {
+                return Err(WinError::new(
+                    WslError::Aborted.code(),
+                    "the event channel is closed",
+                ))
+            }
+            None => {}
+        }
+        if state.events.len() >= self.capacity {
+            match self.back_pressure {
+                BackPressure::Block => unreachable!(),
+                BackPressure::DropOldest => {
+                    let dropped = state.events.pop_front();
+                    state.dropped += 1;
+                    log::warn!("Event channel full, dropping {:?}", dropped);
+                }
+                BackPressure::FailHook => {
+                    log::warn!("Event channel full, failing the hook for {:?}", event);
+                    return Err(WinError::new(
+                        WslError::Busy.code(),
+                        "the event channel is full",
+                    ));
+                }
+            }
+        }
+        state.events.push_back(event);
+        drop(state);
+        self.queue.not_empty.notify_one();
+        Ok(None)
+    }
+
+    /// Number of events discarded by [`BackPressure::DropOldest`].
+    pub fn dropped_events(&self) -> u64 {
+        self.queue.state.lock().unwrap().dropped
+    }
+
+    // Joins the worker once the queued events are handled, if no VM is running and it is done in
+    // time
+    fn go_idle(&self) {
+        let mut worker = self.worker.lock().unwrap();
+        // A VM may have started since the last one stopped
+        if !self.sessions.lock().unwrap().is_empty() {
+            return;
+        }
+        if let WorkerState::Running(handle) = std::mem::replace(&mut *worker, WorkerState::Stopped)
+        {
+            self.queue.close(Closed::Idle);
+            if !self.queue.wait_stopped(self.stop_timeout) {
+                log::warn!(
+                    "The event worker is still busy after {:?}, it stops on its own once done",
+                    self.stop_timeout
+                );
+                *worker = WorkerState::Running(handle);
+                return;
+            }
+            match join(handle) {
+                // Unless the worker shut the channel down meanwhile, it already got `on_shutdown`
+                Some((idle, Some(Closed::Idle))) if !self.queue.is_shut_down() => {
+                    *worker = WorkerState::Idle(idle)
+                }
+                _ => self.queue.close(Closed::Shutdown),
+            }
+        }
+    }
+
+    /// Closes the channel and waits for the worker to handle the queued events and return.
+    ///
+    /// Later hooks fail with [`WslError::Aborted`]. Calling it again, or from the worker itself,
+    /// only closes the channel. Must not be called from `DllMain`, see [`EventChannel`].
+    pub fn shutdown(&self) {
+        // The worker must not wait for the lock, `on_vm_stopping` may hold it while joining it
+        if *self.worker_thread.lock().unwrap() == Some(thread::current().id()) {
+            self.queue.close(Closed::Shutdown);
+            return;
+        }
+        let mut worker = self.worker.lock().unwrap();
+        self.queue.close(Closed::Shutdown);
+        match std::mem::replace(&mut *worker, WorkerState::Stopped) {
+            WorkerState::Running(handle) => {
+                // It went idle past the timeout of `go_idle`, too late for `on_shutdown`
+                if let Some((mut idle, Some(Closed::Idle))) = join(handle) {
+                    idle.on_shutdown();
+                }
+            }
+            WorkerState::Idle(mut idle) => idle.on_shutdown(),
+            WorkerState::Stopped => {}
+        }
+    }
+}
+
+impl<W: EventWorker> Drop for EventChannel<W> {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl<W: EventWorker> WSLPluginV1<'static> for EventChannel<W> {
+    fn try_new(api: ApiV1<'static>) -> Result<Self> {
+        let worker = W::try_new(api)?;
+        Ok(Self::spawn(worker)?)
+    }
+
+    fn on_vm_started(
+        &self,
+        session: &WSLSessionInformation,
+        user_settings: &WSLVmCreationSettings,
+    ) -> Result<()> {
+        self.sessions.lock().unwrap().insert(session.id());
+        self.send(WslEvent::VmStarted {
+            session: OwnedSessionInfo::from(session),
+            custom_configuration_flags: user_settings.custom_configuration_flags(),
+        })
+    }
+
+    fn on_vm_stopping(&self, session: &WSLSessionInformation) -> Result<()> {
+        let result = self.send(WslEvent::VmStopping {
+            session: OwnedSessionInfo::from(session),
+        });
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.remove(&session.id());
+        if sessions.is_empty() {
+            drop(sessions);
+            self.go_idle();
+        }
+        result
+    }
+
+    fn on_distribution_started(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &DistributionInformation,
+    ) -> Result<()> {
+        self.send(WslEvent::DistributionStarted {
+            session: OwnedSessionInfo::from(session),
+            distribution: OwnedDistributionInfo::from(distribution),
+        })
+    }
+
+    fn on_distribution_stopping(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &DistributionInformation,
+    ) -> Result<()> {
+        self.send(WslEvent::DistributionStopping {
+            session: OwnedSessionInfo::from(session),
+            distribution: OwnedDistributionInfo::from(distribution),
+        })
+    }
+
+    #[cfg(feature = "api-2-1-2")]
+    fn on_distribution_registered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &OfflineDistributionInformation,
+    ) -> Result<()> {
+        self.send(WslEvent::DistributionRegistered {
+            session: OwnedSessionInfo::from(session),
+            distribution: OwnedOfflineDistributionInfo::from(distribution),
+        })
+    }
+
+    #[cfg(feature = "api-2-1-2")]
+    fn on_distribution_unregistered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &OfflineDistributionInformation,
+    ) -> Result<()> {
+        self.send(WslEvent::DistributionUnregistered {
+            session: OwnedSessionInfo::from(session),
+            distribution: OwnedOfflineDistributionInfo::from(distribution),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::thread::ThreadId;
+    use std::time::Duration;
+
+    // Reports each event, then waits for the test to let it go on when gated
+    struct Recorder {
+        events: Sender<WslEvent>,
+        gate: Option<Receiver<()>>,
+    }
+
+    impl EventWorker for Recorder {
+        // Only built by the tests
+        fn try_new(_api: ApiV1<'static>) -> Result<Self> {
+            Err(WslError::NotImplemented.into())
+        }
+
+        fn handle(&mut self, event: WslEvent) {
+            let stop = matches!(event, WslEvent::VmStopping { session } if session.id() == 0);
+            self.events.send(event).unwrap();
+            if let Some(gate) = &self.gate {
+                let _ = gate.recv();
+            }
+            assert!(!stop, "asked to panic");
+        }
+    }
+
+    fn stopping(session_id: u32) -> WslEvent {
+        let session = mock::session(session_id);
+        WslEvent::VmStopping {
+            session: OwnedSessionInfo::from(&WSLSessionInformation::from(&session)),
+        }
+    }
+
+    // Channel of capacity 1 whose worker is busy with the first event and has the second queued
+    fn saturated(
+        back_pressure: BackPressure,
+    ) -> (EventChannel<Recorder>, Receiver<WslEvent>, Sender<()>) {
+        let (events, handled) = mpsc::channel();
+        let (open, gate) = mpsc::channel();
+        let channel = EventChannel::with_config(
+            Recorder {
+                events,
+                gate: Some(gate),
+            },
+            1,
+            back_pressure,
+        )
+        .unwrap();
+        channel.send(stopping(1)).unwrap();
+        assert_eq!(handled.recv().unwrap(), stopping(1));
+        channel.send(stopping(2)).unwrap();
+        (channel, handled, open)
+    }
+
+    #[test]
+    fn test_hooks_are_forwarded_in_order() {
+        let (events, handled) = mpsc::channel();
+        let channel = EventChannel::spawn(Recorder { events, gate: None }).unwrap();
+        let session = mock::session(3);
+        let session = WSLSessionInformation::from(&session);
+        let settings = wslplugins_sys::WSLVmCreationSettings {
+            CustomConfigurationFlags: 0,
+        };
+        channel
+            .on_vm_started(&session, &WSLVmCreationSettings::from(&settings))
+            .unwrap();
+        channel.on_vm_stopping(&session).unwrap();
+        drop(channel);
+        let handled: Vec<_> = handled.iter().collect();
+        assert_eq!(handled.len(), 2);
+        assert!(matches!(handled[0], WslEvent::VmStarted { .. }));
+        assert_eq!(handled[0].session().id(), 3);
+        assert_eq!(handled[1], stopping(3));
+    }
+
+    #[test]
+    fn test_block() {
+        let (channel, handled, open) = saturated(BackPressure::Block);
+        let sent = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                channel.send(stopping(3)).unwrap();
+                sent.store(true, Ordering::SeqCst);
+            });
+            thread::sleep(Duration::from_millis(50));
+            assert!(!sent.load(Ordering::SeqCst));
+            open.send(()).unwrap();
+        });
+        assert!(sent.load(Ordering::SeqCst));
+        drop(open);
+        drop(channel);
+        assert_eq!(
+            handled.iter().collect::<Vec<_>>(),
+            [stopping(2), stopping(3)]
+        );
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let (channel, handled, open) = saturated(BackPressure::DropOldest);
+        channel.send(stopping(3)).unwrap();
+        channel.send(stopping(4)).unwrap();
+        assert_eq!(channel.dropped_events(), 2);
+        drop(open);
+        drop(channel);
+        assert_eq!(handled.iter().collect::<Vec<_>>(), [stopping(4)]);
+    }
+
+    #[test]
+    fn test_fail_hook() {
+        let (channel, handled, open) = saturated(BackPressure::FailHook);
+        let err = channel.send(stopping(3)).unwrap_err();
+        assert_eq!(WslError::from(err), WslError::Busy);
+        assert_eq!(channel.dropped_events(), 0);
+        drop(open);
+        drop(channel);
+        assert_eq!(handled.iter().collect::<Vec<_>>(), [stopping(2)]);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let (events, handled) = mpsc::channel();
+        let channel = EventChannel::spawn(Recorder { events, gate: None }).unwrap();
+        channel.send(stopping(1)).unwrap();
+        channel.shutdown();
+        assert_eq!(handled.try_iter().collect::<Vec<_>>(), [stopping(1)]);
+        let err = channel.send(stopping(2)).unwrap_err();
+        assert_eq!(WslError::from(err), WslError::Aborted);
+        channel.shutdown();
+    }
+
+    #[test]
+    fn test_worker_panic_closes_the_channel() {
+        let (events, handled) = mpsc::channel();
+        let channel = EventChannel::spawn(Recorder { events, gate: None }).unwrap();
+        channel.send(stopping(0)).unwrap();
+        assert_eq!(handled.recv().unwrap(), stopping(0));
+        // The worker is gone, so even a blocking send has to give up
+        while channel.send(stopping(1)).is_ok() {
+            thread::yield_now();
+        }
+        channel.shutdown();
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Call {
+        Handled(u32, ThreadId),
+        Idle,
+        Shutdown,
+    }
+
+    struct Tracer {
+        calls: Sender<Call>,
+    }
+
+    impl EventWorker for Tracer {
+        // Only built by the tests
+        fn try_new(_api: ApiV1<'static>) -> Result<Self> {
+            Err(WslError::NotImplemented.into())
+        }
+
+        fn handle(&mut self, event: WslEvent) {
+            let call = Call::Handled(event.session().id(), thread::current().id());
+            self.calls.send(call).unwrap();
+        }
+
+        fn on_idle(&mut self) {
+            self.calls.send(Call::Idle).unwrap();
+        }
+
+        fn on_shutdown(&mut self) {
+            self.calls.send(Call::Shutdown).unwrap();
+        }
+    }
+
+    // The plugin is never dropped and nothing may be joined under the loader lock when the DLL
+    // unloads, so the worker has to be gone once WSL stopped the VMs
+    #[test]
+    fn test_worker_stops_with_the_last_vm() {
+        let (calls, received) = mpsc::channel();
+        let channel = EventChannel::spawn(Tracer { calls }).unwrap();
+        let (first, second) = (mock::session(1), mock::session(2));
+        let (first, second) = (
+            WSLSessionInformation::from(&first),
+            WSLSessionInformation::from(&second),
+        );
+        let settings = wslplugins_sys::WSLVmCreationSettings {
+            CustomConfigurationFlags: 0,
+        };
+        let settings = WSLVmCreationSettings::from(&settings);
+        channel.on_vm_started(&first, &settings).unwrap();
+        channel.on_vm_started(&second, &settings).unwrap();
+        channel.on_vm_stopping(&first).unwrap();
+        channel.on_vm_stopping(&second).unwrap();
+        // Joined before the last hook returned
+        let run: Vec<_> = received.try_iter().collect();
+        let Call::Handled(_, worker_thread) = run[0] else {
+            panic!("unexpected {:?}", run[0]);
+        };
+        assert_eq!(
+            run,
+            [
+                Call::Handled(1, worker_thread),
+                Call::Handled(2, worker_thread),
+                Call::Handled(1, worker_thread),
+                Call::Handled(2, worker_thread),
+                Call::Idle,
+            ]
+        );
+
+        // The next hook starts a new thread
+        channel.on_vm_started(&first, &settings).unwrap();
+        drop(channel);
+        let run: Vec<_> = received.iter().collect();
+        assert!(
+            matches!(run[..], [Call::Handled(1, thread), Call::Shutdown] if thread != worker_thread)
+        );
+    }
+
+    #[test]
+    fn test_shutdown_when_idle() {
+        let (calls, received) = mpsc::channel();
+        let channel = EventChannel::spawn(Tracer { calls }).unwrap();
+        let session = mock::session(1);
+        channel
+            .on_vm_stopping(&WSLSessionInformation::from(&session))
+            .unwrap();
+        channel.shutdown();
+        assert_eq!(received.try_iter().count(), 3);
+        let err = channel.send(stopping(1)).unwrap_err();
+        assert_eq!(WslError::from(err), WslError::Aborted);
+        drop(channel);
+        assert_eq!(received.try_iter().count(), 0);
+    }
+
+    // Takes 100ms per event
+    struct Slow<const STOP_TIMEOUT_MS: u64> {
+        calls: Sender<Call>,
+    }
+
+    impl<const STOP_TIMEOUT_MS: u64> EventWorker for Slow<STOP_TIMEOUT_MS> {
+        const STOP_TIMEOUT: Duration = Duration::from_millis(STOP_TIMEOUT_MS);
+
+        // Only built by the tests
+        fn try_new(_api: ApiV1<'static>) -> Result<Self> {
+            Err(WslError::NotImplemented.into())
+        }
+
+        fn handle(&mut self, event: WslEvent) {
+            thread::sleep(Duration::from_millis(100));
+            let call = Call::Handled(event.session().id(), thread::current().id());
+            self.calls.send(call).unwrap();
+        }
+
+        fn on_idle(&mut self) {
+            self.calls.send(Call::Idle).unwrap();
+        }
+
+        fn on_shutdown(&mut self) {
+            self.calls.send(Call::Shutdown).unwrap();
+        }
+    }
+
+    // Starts a VM and queues two more events, then times the stop of the VM
+    fn stop_with_backlog<W: EventWorker>(channel: &EventChannel<W>) -> Duration {
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let settings = wslplugins_sys::WSLVmCreationSettings {
+            CustomConfigurationFlags: 0,
+        };
+        channel
+            .on_vm_started(&session, &WSLVmCreationSettings::from(&settings))
+            .unwrap();
+        channel.send(stopping(2)).unwrap();
+        channel.send(stopping(3)).unwrap();
+        let start = std::time::Instant::now();
+        channel.on_vm_stopping(&session).unwrap();
+        start.elapsed()
+    }
+
+    // The last `on_vm_stopping` holds WSL back until the whole backlog is handled
+    #[test]
+    fn test_vm_stopping_waits_for_the_backlog() {
+        let (calls, received) = mpsc::channel();
+        let channel = EventChannel::spawn(Slow::<5000> { calls }).unwrap();
+        let elapsed = stop_with_backlog(&channel);
+        assert!(elapsed >= Duration::from_millis(250), "{:?}", elapsed);
+        let run: Vec<_> = received.try_iter().collect();
+        assert_eq!(run.len(), 5);
+        assert_eq!(run[4], Call::Idle);
+    }
+
+    #[test]
+    fn test_vm_stopping_wait_is_bounded() {
+        let (calls, received) = mpsc::channel();
+        let channel = EventChannel::spawn(Slow::<50> { calls }).unwrap();
+        let elapsed = stop_with_backlog(&channel);
+        assert!(elapsed < Duration::from_millis(250), "{:?}", elapsed);
+        assert!(!received.try_iter().any(|call| call == Call::Idle));
+
+        // The next event waits for the busy thread to end, then starts a new one
+        channel.send(stopping(4)).unwrap();
+        drop(channel);
+        let run: Vec<_> = received.iter().collect();
+        let (Call::Handled(_, first), Call::Handled(_, second)) = (&run[0], &run[5]) else {
+            panic!("unexpected {:?}", run);
+        };
+        assert_ne!(first, second);
+        assert_eq!(
+            run,
+            [
+                Call::Handled(1, *first),
+                Call::Handled(2, *first),
+                Call::Handled(3, *first),
+                Call::Handled(1, *first),
+                Call::Idle,
+                Call::Handled(4, *second),
+                Call::Shutdown,
+            ]
+        );
+    }
+
+    struct Counter;
+
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+    impl EventWorker for Counter {
+        fn try_new(api: ApiV1<'static>) -> Result<Self> {
+            assert_eq!(api.version().major(), 2);
+            Ok(Counter)
+        }
+
+        fn handle(&mut self, _event: WslEvent) {}
+
+        fn on_shutdown(&mut self) {
+            SHUTDOWN.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_try_new() {
+        let api: &'static _ = Box::leak(Box::new(mock::api(2, 1, 3)));
+        let channel = EventChannel::<Counter>::try_new(ApiV1::from(api)).unwrap();
+        let session = mock::session(1);
+        channel
+            .on_vm_stopping(&WSLSessionInformation::from(&session))
+            .unwrap();
+        drop(channel);
+        assert!(SHUTDOWN.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::windows::core::{Error as WinError, HRESULT};
+use crate::WslError;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::net::{Shutdown, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Limits applied while reading the output of an executed program.
+///
+/// Hooks run synchronously inside the WSL service, a hung program would otherwise stall it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ExecOptions {
+    /// Time allowed for the program to write its whole output and close the socket.
+    pub timeout: Option<Duration>,
+    /// Maximum number of bytes buffered from the program.
+    pub max_output_bytes: Option<usize>,
+}
+
+impl ExecOptions {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_output_bytes(mut self, max_output_bytes: usize) -> Self {
+        self.max_output_bytes = Some(max_output_bytes);
+        self
+    }
+}
+
+/// Failure of an execution bounded by [`ExecOptions`].
+#[derive(Debug)]
+pub enum ExecError {
+    /// The program did not complete in time, the socket has been shut down.
+    Timeout(Duration),
+    /// The program wrote more than allowed, the socket has been shut down.
+    OutputTooLarge(usize),
+    /// WSL failed to start the program.
+    Wsl(WinError),
+    Io(io::Error),
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Timeout(timeout) => {
+                write!(f, "the program did not complete within {:?}", timeout)
+            }
+            ExecError::OutputTooLarge(limit) => {
+                write!(f, "the program wrote more than {} bytes", limit)
+            }
+            ExecError::Wsl(err) => write!(f, "unable to execute the program: {}", err),
+            ExecError::Io(err) => write!(f, "unable to read the program output: {}", err),
+        }
+    }
+}
+
+impl Error for ExecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExecError::Wsl(err) => Some(err),
+            ExecError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<WinError> for ExecError {
+    fn from(err: WinError) -> Self {
+        ExecError::Wsl(err)
+    }
+}
+
+impl From<WslError> for ExecError {
+    fn from(err: WslError) -> Self {
+        ExecError::Wsl(err.into())
+    }
+}
+
+impl From<io::Error> for ExecError {
+    fn from(err: io::Error) -> Self {
+        ExecError::Io(err)
+    }
+}
+
+impl From<ExecError> for WinError {
+    fn from(err: ExecError) -> Self {
+        match err {
+            ExecError::Timeout(_) => WslError::Timeout.into(),
+            ExecError::OutputTooLarge(_) => WslError::OutOfMemory.into(),
+            ExecError::Wsl(err) => err,
+            ExecError::Io(err) => err.into(),
+        }
+    }
+}
+
+impl From<ExecError> for HRESULT {
+    fn from(err: ExecError) -> Self {
+        WinError::from(err).code()
+    }
+}
+
+/// Reads `stream` to its end within the limits of `options`.
+///
+/// The stream is shut down when a limit is hit, so the program sees EOF on its stdin and EPIPE
+/// on its stdout. The wrapper of [`WslCommand::output_with_options`](crate::WslCommand::output_with_options)
+/// kills the program it runs on that EOF, since the program only writes to temporary files.
+pub(crate) fn read_to_end(
+    mut stream: TcpStream,
+    options: &ExecOptions,
+) -> Result<Vec<u8>, ExecError> {
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut output = Vec::new();
+    let mut buffer = [0u8; 8192];
+    loop {
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(abort(&stream, ExecError::Timeout(options.timeout.unwrap())));
+            }
+            stream.set_read_timeout(Some(remaining))?;
+        }
+        let read = match stream.read(&mut buffer) {
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Err(abort(&stream, ExecError::Timeout(options.timeout.unwrap())));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if read == 0 {
+            return Ok(output);
+        }
+        if let Some(limit) = options.max_output_bytes {
+            if output.len() + read > limit {
+                return Err(abort(&stream, ExecError::OutputTooLarge(limit)));
+            }
+        }
+        output.extend_from_slice(&buffer[..read]);
+    }
+}
+
+fn abort(stream: &TcpStream, err: ExecError) -> ExecError {
+    log::warn!("Aborting execution: {}", err);
+    // The peer may already be gone, the original error is what matters
+    let _ = stream.shutdown(Shutdown::Both);
+    err
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    #[test]
+    fn test_read_to_end() {
+        let (client, mut server) = pair();
+        server.write_all(b"hello").unwrap();
+        drop(server);
+        let options = ExecOptions::default()
+            .with_timeout(Duration::from_secs(10))
+            .with_max_output_bytes(5);
+        assert_eq!(read_to_end(client, &options).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_timeout_shuts_down_socket() {
+        let (client, mut server) = pair();
+        let (sender, receiver) = mpsc::channel();
+        let peer = thread::spawn(move || {
+            server.write_all(b"partial").unwrap();
+            let mut buffer = [0u8; 1];
+            // Blocks until the plugin side gives up
+            sender.send(server.read(&mut buffer).unwrap()).unwrap();
+        });
+        let options = ExecOptions::default().with_timeout(Duration::from_millis(50));
+        let err = read_to_end(client, &options).unwrap_err();
+        assert!(matches!(err, ExecError::Timeout(timeout) if timeout == Duration::from_millis(50)));
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)).unwrap(), 0);
+        peer.join().unwrap();
+        assert_eq!(HRESULT::from(err), WslError::Timeout.code());
+    }
+
+    // The wrapper run by `WslCommand::output_with_options`, as WSL would on the other end
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_wrapped_program() {
+        use std::os::fd::OwnedFd;
+        use std::process::{Command, Stdio};
+        let (client, server) = pair();
+        let argv = crate::wsl_output::wrap_argv(vec!["/bin/sleep".into(), "30".into()]);
+        let mut wrapper = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::from(OwnedFd::from(server.try_clone().unwrap())))
+            .stdout(Stdio::from(OwnedFd::from(server)))
+            .spawn()
+            .unwrap();
+        let options = ExecOptions::default().with_timeout(Duration::from_millis(200));
+        let err = read_to_end(client, &options).unwrap_err();
+        assert!(matches!(err, ExecError::Timeout(_)));
+        // The wrapper only returns once the program is gone
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while wrapper.try_wait().unwrap().is_none() {
+            assert!(
+                Instant::now() < deadline,
+                "the program outlived the deadline"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_output_too_large() {
+        let (client, mut server) = pair();
+        server.write_all(b"0123456789").unwrap();
+        let options = ExecOptions::default().with_max_output_bytes(4);
+        let err = read_to_end(client, &options).unwrap_err();
+        assert!(matches!(err, ExecError::OutputTooLarge(4)));
+        let mut rest = Vec::new();
+        server.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+    }
+}
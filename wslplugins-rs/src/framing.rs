@@ -0,0 +1,308 @@
+//! Length-prefixed frames over the socket returned by [`ApiV1::execute_binary`](crate::ApiV1::execute_binary).
+//!
+//! Each frame is its payload length as a big-endian `u32` followed by the payload. The end of the
+//! stream between two frames is a clean shutdown of the peer, anywhere else it is an error.
+//!
+//! With the `serde` feature, [`Channel`] exchanges typed messages encoded as JSON, and [`serve`]
+//! with [`Stdio`] is the matching side of a helper running in the distribution, whose stdin and
+//! stdout are the socket.
+use std::io::{self, Read, Write};
+#[cfg(feature = "serde")]
+use std::marker::PhantomData;
+#[cfg(feature = "serde")]
+use std::net::{Shutdown, TcpStream};
+#[cfg(all(feature = "serde", unix))]
+use std::os::unix::net::UnixStream;
+
+/// Largest payload accepted, protects both sides from allocating on a corrupted length.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+fn check_len(len: usize) -> io::Result<()> {
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "frame of {} bytes exceeds the limit of {} bytes",
+                len, MAX_FRAME_LEN
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Writes `payload` as a single frame and flushes `writer`.
+pub fn write_frame<W: Write>(writer: &mut W, payload: &[u8]) -> io::Result<()> {
+    check_len(payload.len())?;
+    // One write per frame, the header alone would wait for the payload on a TCP socket
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Reads the next frame, `None` when the peer has shut down its side.
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 4];
+    let mut read = 0;
+    while read < header.len() {
+        match reader.read(&mut header[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated frame header",
+                ))
+            }
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    let len = u32::from_be_bytes(header) as usize;
+    check_len(len)?;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// Typed two-way channel, sends `Req` and receives `Resp`, encoded as JSON frames.
+///
+/// The peer uses a `Channel<Resp, Req>`, or [`serve`] when it only answers requests.
+#[cfg(feature = "serde")]
+pub struct Channel<Req, Resp, S = TcpStream> {
+    stream: S,
+    _messages: PhantomData<fn(Req) -> Resp>,
+}
+
+#[cfg(feature = "serde")]
+impl<Req, Resp, S> Channel<Req, Resp, S>
+where
+    Req: serde::Serialize,
+    Resp: serde::de::DeserializeOwned,
+    S: Read + Write,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            _messages: PhantomData,
+        }
+    }
+
+    pub fn send(&mut self, message: &Req) -> io::Result<()> {
+        write_frame(&mut self.stream, &serde_json::to_vec(message)?)
+    }
+
+    /// Receives the next message, `None` when the peer has shut down its side.
+    pub fn recv(&mut self) -> io::Result<Option<Resp>> {
+        read_frame(&mut self.stream)?
+            .map(|payload| serde_json::from_slice(&payload).map_err(io::Error::from))
+            .transpose()
+    }
+
+    /// Sends `request` and waits for its response.
+    pub fn request(&mut self, request: &Req) -> io::Result<Resp> {
+        self.send(request)?;
+        self.recv()?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the channel was closed before the response",
+            )
+        })
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Req, Resp> Channel<Req, Resp, TcpStream> {
+    /// Shuts down the sending side, the peer gets a clean end of stream once it has read the
+    /// pending messages while the responses can still be received.
+    pub fn shutdown(&self) -> io::Result<()> {
+        self.stream.shutdown(Shutdown::Write)
+    }
+}
+
+#[cfg(all(feature = "serde", unix))]
+impl<Req, Resp> Channel<Req, Resp, UnixStream> {
+    /// Shuts down the sending side, see [`Channel::shutdown`].
+    pub fn shutdown(&self) -> io::Result<()> {
+        self.stream.shutdown(Shutdown::Write)
+    }
+}
+
+/// Answers each request received on `stream` with `handler`, until the plugin shuts down its
+/// side.
+#[cfg(feature = "serde")]
+pub fn serve<Req, Resp, S, F>(stream: S, mut handler: F) -> io::Result<()>
+where
+    Req: serde::de::DeserializeOwned,
+    Resp: serde::Serialize,
+    S: Read + Write,
+    F: FnMut(Req) -> Resp,
+{
+    let mut channel = Channel::<Resp, Req, S>::new(stream);
+    while let Some(request) = channel.recv()? {
+        channel.send(&handler(request))?;
+    }
+    Ok(())
+}
+
+/// Standard input and output of a helper launched through
+/// [`ApiV1::execute_binary`](crate::ApiV1::execute_binary), both connected to the socket of the
+/// plugin.
+pub struct Stdio {
+    stdin: io::StdinLock<'static>,
+    stdout: io::StdoutLock<'static>,
+}
+
+impl Stdio {
+    pub fn new() -> Self {
+        Self {
+            stdin: io::stdin().lock(),
+            stdout: io::stdout().lock(),
+        }
+    }
+}
+
+impl Default for Stdio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Read for Stdio {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stdin.read(buf)
+    }
+}
+
+impl Write for Stdio {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"hello").unwrap();
+        write_frame(&mut buffer, b"").unwrap();
+        assert_eq!(&buffer[..9], b"\0\0\0\x05hello");
+        let mut reader = &buffer[..];
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"hello");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"");
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let err = read_frame(&mut &b"\0\0"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read_frame(&mut &b"\0\0\0\x05hel"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_too_large() {
+        let err = read_frame(&mut &b"\xff\xff\xff\xff"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = write_frame(&mut Vec::new(), &vec![0; MAX_FRAME_LEN + 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socketpair() {
+        use std::os::unix::net::UnixStream;
+        let (mut plugin, mut helper) = UnixStream::pair().unwrap();
+        let helper = std::thread::spawn(move || {
+            while let Some(frame) = read_frame(&mut helper).unwrap() {
+                write_frame(&mut helper, &frame.repeat(2)).unwrap();
+            }
+        });
+        write_frame(&mut plugin, b"ab").unwrap();
+        assert_eq!(read_frame(&mut plugin).unwrap().unwrap(), b"abab");
+        plugin.shutdown(std::net::Shutdown::Write).unwrap();
+        helper.join().unwrap();
+        assert_eq!(read_frame(&mut plugin).unwrap(), None);
+    }
+
+    #[cfg(all(feature = "serde", unix))]
+    mod channel {
+        use super::super::*;
+        use serde::{Deserialize, Serialize};
+        use std::thread;
+
+        #[derive(Debug, Serialize, Deserialize)]
+        enum Request {
+            Add(i32, i32),
+            Echo(String),
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Response {
+            Sum(i32),
+            Echo(String),
+        }
+
+        #[test]
+        fn test_request_and_shutdown() {
+            let (plugin, helper) = UnixStream::pair().unwrap();
+            let helper = thread::spawn(move || {
+                serve(helper, |request| match request {
+                    Request::Add(a, b) => Response::Sum(a + b),
+                    Request::Echo(text) => Response::Echo(text),
+                })
+            });
+            let mut channel = Channel::<Request, Response, _>::new(plugin);
+            assert_eq!(
+                channel.request(&Request::Add(2, 3)).unwrap(),
+                Response::Sum(5)
+            );
+            // Pipelined, then shut down before reading the responses
+            channel.send(&Request::Echo("one".into())).unwrap();
+            channel.send(&Request::Echo("two".into())).unwrap();
+            channel.shutdown().unwrap();
+            assert_eq!(channel.recv().unwrap(), Some(Response::Echo("one".into())));
+            assert_eq!(channel.recv().unwrap(), Some(Response::Echo("two".into())));
+            assert_eq!(channel.recv().unwrap(), None);
+            helper.join().unwrap().unwrap();
+        }
+
+        #[test]
+        fn test_peer_gone() {
+            let (plugin, helper) = UnixStream::pair().unwrap();
+            drop(helper);
+            let mut channel = Channel::<Request, Response, _>::new(plugin);
+            let err = channel.request(&Request::Add(1, 1)).unwrap_err();
+            assert!(matches!(
+                err.kind(),
+                io::ErrorKind::UnexpectedEof | io::ErrorKind::BrokenPipe
+            ));
+        }
+
+        #[test]
+        fn test_invalid_message() {
+            let (plugin, mut helper) = UnixStream::pair().unwrap();
+            write_frame(&mut helper, b"not json").unwrap();
+            let mut channel = Channel::<Request, Response, _>::new(plugin);
+            let err = channel.recv().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::{E_POINTER, E_UNEXPECTED};
+use crate::{ApiV1, WslError};
+use log::error;
+use std::any::Any;
+use std::ffi::OsStr;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use wslplugins_sys::WSLPluginAPIV1;
+
+/// Runs the body of a generated hook so a panic does not unwind into WSL.
+///
+/// A panic is logged and turned into `E_UNEXPECTED`. Its message is also reported through
+/// [`ApiV1::plugin_error`] when `api` is given, which WSL only accepts from the hooks able to fail
+/// the VM or distribution start.
+pub fn catch_hook_panic<F: FnOnce() -> HRESULT>(
+    hook: &str,
+    api: Option<&WSLPluginAPIV1>,
+    body: F,
+) -> HRESULT {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = panic_message(hook, payload.as_ref());
+        error!("{}", message);
+        if let Some(api) = api {
+            // Best effort, E_UNEXPECTED is what WSL has to see
+            let _ = ApiV1::from(api).plugin_error(OsStr::new(&message));
+        }
+        E_UNEXPECTED
+    })
+}
+
+/// Runs the body of a generated hook, logging a panic before aborting the WSL service.
+pub fn abort_on_hook_panic<F: FnOnce() -> HRESULT>(hook: &str, body: F) -> HRESULT {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        error!("{}, aborting", panic_message(hook, payload.as_ref()));
+        std::process::abort()
+    })
+}
+
+/// Logs a null argument received by a generated hook and returns `E_POINTER`.
+pub fn null_hook_argument(hook: &str, argument: &str) -> HRESULT {
+    error!("{} called with a null {}", hook, argument);
+    E_POINTER
+}
+
+/// Logs a hook called while there is no plugin, before `try_new` succeeded or after it failed,
+/// and returns [`WslError::IllegalMethodCall`].
+pub fn plugin_not_initialized(hook: &str) -> HRESULT {
+    error!("{} called while the plugin is not created", hook);
+    WslError::IllegalMethodCall.code()
+}
+
+fn panic_message(hook: &str, payload: &(dyn Any + Send)) -> String {
+    let reason = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown reason");
+    format!("{} panicked: {}", hook, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Call};
+    use crate::windows::Win32::Foundation::{E_FAIL, S_OK};
+
+    #[test]
+    fn test_no_panic() {
+        let api = mock::api(2, 1, 3);
+        assert_eq!(
+            catch_hook_panic("on_vm_started", Some(&api), || E_FAIL),
+            E_FAIL
+        );
+        assert_eq!(abort_on_hook_panic("on_vm_started", || S_OK), S_OK);
+        assert_eq!(mock::calls(), []);
+    }
+
+    #[test]
+    fn test_panic_is_reported() {
+        let api = mock::api(2, 1, 3);
+        let code = catch_hook_panic("on_vm_started", Some(&api), || panic!("boom {}", 42));
+        assert_eq!(code, E_UNEXPECTED);
+        assert_eq!(
+            mock::calls(),
+            [Call::PluginError("on_vm_started panicked: boom 42".into())]
+        );
+    }
+
+    #[test]
+    fn test_panic_without_plugin_error() {
+        let code = catch_hook_panic("on_vm_stopping", None, || panic!("boom"));
+        assert_eq!(code, E_UNEXPECTED);
+        assert_eq!(mock::calls(), []);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        assert_eq!(null_hook_argument("on_vm_started", "session"), E_POINTER);
+        assert_eq!(
+            WslError::from(plugin_not_initialized("on_vm_started")),
+            WslError::IllegalMethodCall
+        );
+    }
+
+    #[test]
+    fn test_panic_message() {
+        assert_eq!(
+            panic_message("create_plugin", &"static message"),
+            "create_plugin panicked: static message"
+        );
+        assert_eq!(
+            panic_message("create_plugin", &String::from("owned message")),
+            "create_plugin panicked: owned message"
+        );
+        assert_eq!(
+            panic_message("create_plugin", &42),
+            "create_plugin panicked: unknown reason"
+        );
+    }
+}
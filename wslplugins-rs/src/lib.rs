@@ -1,9 +1,37 @@
 pub extern crate wslplugins_sys;
+#[cfg(feature = "serde")]
+pub mod agent;
 mod api_v1;
+mod api_v1_capabilities;
+#[cfg(feature = "tokio")]
+mod async_exec;
+#[cfg(feature = "auto-mount")]
+pub mod auto_mount;
 mod core_distribution_information;
 mod distribution_information;
+mod event_channel;
+mod exec_options;
+pub mod framing;
+mod hook_guard;
+mod lifecycle_tracker;
+#[cfg(test)]
+mod mock;
+mod mount_registry;
+mod mount_spec;
+#[cfg(feature = "api-2-1-2")]
 mod offline_distribution_information;
+mod owned_distribution_info;
+#[cfg(feature = "api-2-1-2")]
+mod owned_offline_distribution_info;
+mod owned_session_info;
+mod platform;
+#[cfg(feature = "serde")]
+mod serde_utils;
 mod utils;
+mod version_requirement_error;
+mod wsl_command;
+mod wsl_error;
+mod wsl_output;
 mod wsl_plugin_v1;
 mod wsl_session_information;
 mod wsl_user_configuration;
@@ -11,9 +39,30 @@ mod wsl_version;
 mod wsl_vm_creation_settings;
 
 pub use api_v1::ApiV1;
+pub use api_v1_capabilities::{ApiEntryPoint, ApiV1Capabilities, NotSupportedError};
+#[cfg(feature = "tokio")]
+pub use async_exec::run_with_deadline;
 pub use core_distribution_information::CoreDistributionInformation;
 pub use distribution_information::DistributionInformation;
-pub use utils::create_plugin_with_required_version;
+pub use event_channel::{BackPressure, EventChannel, EventWorker, WslEvent};
+pub use exec_options::{ExecError, ExecOptions};
+pub use hook_guard::{
+    abort_on_hook_panic, catch_hook_panic, null_hook_argument, plugin_not_initialized,
+};
+pub use lifecycle_tracker::{LifecycleTracker, TrackedDistribution};
+pub use mount_registry::{MountError, MountRegistry};
+pub use mount_spec::{MountSpec, MountSpecError};
+#[cfg(feature = "api-2-1-2")]
+pub use offline_distribution_information::OfflineDistributionInformation;
+pub use owned_distribution_info::OwnedDistributionInfo;
+#[cfg(feature = "api-2-1-2")]
+pub use owned_offline_distribution_info::OwnedOfflineDistributionInfo;
+pub use owned_session_info::OwnedSessionInfo;
+pub use utils::{create_named_plugin_with_required_version, create_plugin_with_required_version};
+pub use version_requirement_error::VersionRequirementError;
+pub use wsl_command::WslCommand;
+pub use wsl_error::WslError;
+pub use wsl_output::{WslExitStatus, WslOutput};
 pub use wsl_plugin_v1::WSLPluginV1;
 pub use wsl_session_information::WSLSessionInformation;
 pub use wsl_version::WSLVersion;
@@ -22,3 +71,4 @@ pub use wsl_vm_creation_settings::WSLVmCreationSettings;
 pub use wslplugins_macro::wsl_plugin_v1;
 #[cfg(feature = "sys")]
 pub use wslplugins_sys as sys;
+pub use wslplugins_sys::windows;
@@ -0,0 +1,384 @@
+#[cfg(feature = "api-2-1-2")]
+use crate::offline_distribution_information::OfflineDistributionInformation;
+#[cfg(feature = "api-2-1-2")]
+use crate::owned_offline_distribution_info::OwnedOfflineDistributionInfo;
+use crate::windows::core::GUID;
+use crate::{
+    CoreDistributionInformation, DistributionInformation, OwnedDistributionInfo,
+    WSLSessionInformation,
+};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// A distribution running in a session, as last reported by WSL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrackedDistribution {
+    info: OwnedDistributionInfo,
+    stopping_calls: u32,
+}
+
+impl TrackedDistribution {
+    /// Id, name, init PID and PID namespace of the distribution.
+    pub fn info(&self) -> &OwnedDistributionInfo {
+        &self.info
+    }
+
+    /// Whether WSL has announced the distribution is stopping.
+    ///
+    /// Stopping may fail, e.g. when a file is in use, so the distribution may keep running.
+    pub fn is_stopping(&self) -> bool {
+        self.stopping_calls > 0
+    }
+
+    /// Number of `on_distribution_stopping` calls received since it started.
+    pub fn stopping_calls(&self) -> u32 {
+        self.stopping_calls
+    }
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    distributions: HashMap<GUID, TrackedDistribution>,
+    #[cfg(feature = "api-2-1-2")]
+    registered: HashMap<GUID, OwnedOfflineDistributionInfo>,
+}
+
+/// Live sessions and the distributions running in each of them, fed by the hooks of the plugin.
+///
+/// Each `on_*` method is meant to be called from the hook of the same name, the queries can be
+/// made from any thread.
+#[derive(Debug, Default)]
+pub struct LifecycleTracker {
+    sessions: RwLock<HashMap<u32, SessionState>>,
+}
+
+impl LifecycleTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_vm_started(&self, session: &WSLSessionInformation) {
+        let mut sessions = self.sessions.write().unwrap();
+        if sessions
+            .insert(session.id(), SessionState::default())
+            .is_some()
+        {
+            log::warn!("Session {} started again without stopping", session.id());
+        }
+    }
+
+    /// Forgets the session, returns the distributions which were still tracked as running.
+    pub fn on_vm_stopping(&self, session: &WSLSessionInformation) -> Vec<TrackedDistribution> {
+        let state = self.sessions.write().unwrap().remove(&session.id());
+        match state {
+            Some(state) => state.distributions.into_values().collect(),
+            None => {
+                log::warn!("Unknown session {} is stopping", session.id());
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn on_distribution_started(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &DistributionInformation,
+    ) {
+        let info = OwnedDistributionInfo::from(distribution);
+        let mut sessions = self.sessions.write().unwrap();
+        // The plugin may have been loaded after the VM started
+        let state = sessions.entry(session.id()).or_default();
+        state.distributions.insert(
+            *info.id(),
+            TrackedDistribution {
+                info,
+                stopping_calls: 0,
+            },
+        );
+    }
+
+    /// Records the stop request, returns `true` on the first one since the distribution started.
+    ///
+    /// WSL calls the hook again when a stop fails and is retried, the distribution is tracked
+    /// until it starts again or its session stops.
+    pub fn on_distribution_stopping(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &DistributionInformation,
+    ) -> bool {
+        let mut sessions = self.sessions.write().unwrap();
+        match sessions
+            .get_mut(&session.id())
+            .and_then(|state| state.distributions.get_mut(distribution.id()))
+        {
+            Some(tracked) => {
+                tracked.stopping_calls += 1;
+                tracked.stopping_calls == 1
+            }
+            None => {
+                log::warn!("Unknown distribution {:?} is stopping", distribution.name());
+                false
+            }
+        }
+    }
+
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    pub fn on_distribution_registered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &OfflineDistributionInformation,
+    ) {
+        let info = OwnedOfflineDistributionInfo::from(distribution);
+        let mut sessions = self.sessions.write().unwrap();
+        let state = sessions.entry(session.id()).or_default();
+        state.registered.insert(*info.id(), info);
+    }
+
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    pub fn on_distribution_unregistered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &OfflineDistributionInformation,
+    ) {
+        let mut sessions = self.sessions.write().unwrap();
+        if let Some(state) = sessions.get_mut(&session.id()) {
+            state.registered.remove(distribution.id());
+            state.distributions.remove(distribution.id());
+        }
+    }
+
+    pub fn is_live(&self, session_id: u32) -> bool {
+        self.sessions.read().unwrap().contains_key(&session_id)
+    }
+
+    /// Ids of the live sessions, in ascending order.
+    pub fn sessions(&self) -> Vec<u32> {
+        let mut sessions: Vec<u32> = self.sessions.read().unwrap().keys().copied().collect();
+        sessions.sort_unstable();
+        sessions
+    }
+
+    /// Distributions running in the session, stopping ones included.
+    pub fn distributions(&self, session_id: u32) -> Vec<TrackedDistribution> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(&session_id)
+            .map(|state| state.distributions.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn distribution(
+        &self,
+        session_id: u32,
+        distribution_id: &GUID,
+    ) -> Option<TrackedDistribution> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(&session_id)?
+            .distributions
+            .get(distribution_id)
+            .cloned()
+    }
+
+    /// Distributions registered in the session since the plugin was loaded.
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    pub fn registered_distributions(&self, session_id: u32) -> Vec<OwnedOfflineDistributionInfo> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(&session_id)
+            .map(|state| state.registered.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use crate::utils::encode_wide_null_terminated;
+    use crate::windows::core::PCWSTR;
+    use std::ffi::OsStr;
+    use std::sync::Arc;
+    use std::thread;
+
+    const UBUNTU: GUID = GUID::from_u128(0x2b5d_0c8e_1d3a_4b5c_9e0f_1a2b_3c4d_5e6f);
+    const DEBIAN: GUID = GUID::from_u128(0x6f2b_1c2e_4d3a_4b5c_8d9e_0f1a_2b3c_4d5e);
+
+    // Native distribution information with the name it points to
+    struct Distribution {
+        _name: Vec<u16>,
+        native: wslplugins_sys::WSLDistributionInformation,
+    }
+
+    fn distribution(id: GUID, name: &str, init_pid: u32) -> Distribution {
+        let name = encode_wide_null_terminated(OsStr::new(name));
+        let native = wslplugins_sys::WSLDistributionInformation {
+            Id: id,
+            Name: PCWSTR::from_raw(name.as_ptr()),
+            PidNamespace: 4026531836 + init_pid as u64,
+            PackageFamilyName: PCWSTR::null(),
+            InitPid: init_pid,
+        };
+        Distribution {
+            _name: name,
+            native,
+        }
+    }
+
+    impl Distribution {
+        fn info(&self) -> DistributionInformation<'_> {
+            DistributionInformation::from(&self.native)
+        }
+    }
+
+    #[test]
+    fn test_session_lifecycle() {
+        let tracker = LifecycleTracker::new();
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let ubuntu = distribution(UBUNTU, "Ubuntu", 42);
+        let debian = distribution(DEBIAN, "Debian", 7);
+
+        tracker.on_vm_started(&session);
+        assert!(tracker.is_live(1));
+        assert_eq!(tracker.sessions(), [1]);
+        assert!(tracker.distributions(1).is_empty());
+
+        tracker.on_distribution_started(&session, &ubuntu.info());
+        tracker.on_distribution_started(&session, &debian.info());
+        let tracked = tracker.distribution(1, &UBUNTU).unwrap();
+        assert_eq!(tracked.info().name(), "Ubuntu");
+        assert_eq!(tracked.info().init_pid(), 42);
+        assert_eq!(tracked.info().pid_namespace(), 4026531878);
+        assert!(!tracked.is_stopping());
+        assert_eq!(tracker.distributions(1).len(), 2);
+
+        assert!(tracker.on_distribution_stopping(&session, &debian.info()));
+        assert!(tracker.distribution(1, &DEBIAN).unwrap().is_stopping());
+
+        let remaining = tracker.on_vm_stopping(&session);
+        assert_eq!(remaining.len(), 2);
+        assert!(!tracker.is_live(1));
+        assert!(tracker.sessions().is_empty());
+        assert_eq!(tracker.distribution(1, &UBUNTU), None);
+    }
+
+    #[test]
+    fn test_repeated_stopping() {
+        let tracker = LifecycleTracker::new();
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let ubuntu = distribution(UBUNTU, "Ubuntu", 42);
+        tracker.on_vm_started(&session);
+        tracker.on_distribution_started(&session, &ubuntu.info());
+
+        // The first stop fails because a file is in use, WSL retries
+        assert!(tracker.on_distribution_stopping(&session, &ubuntu.info()));
+        assert!(!tracker.on_distribution_stopping(&session, &ubuntu.info()));
+        assert!(!tracker.on_distribution_stopping(&session, &ubuntu.info()));
+        let tracked = tracker.distribution(1, &UBUNTU).unwrap();
+        assert!(tracked.is_stopping());
+        assert_eq!(tracked.stopping_calls(), 3);
+
+        // Started again with a new init process
+        let restarted = distribution(UBUNTU, "Ubuntu", 84);
+        tracker.on_distribution_started(&session, &restarted.info());
+        let tracked = tracker.distribution(1, &UBUNTU).unwrap();
+        assert!(!tracked.is_stopping());
+        assert_eq!(tracked.info().init_pid(), 84);
+        assert!(tracker.on_distribution_stopping(&session, &restarted.info()));
+    }
+
+    #[test]
+    fn test_sessions_are_independent() {
+        let tracker = LifecycleTracker::new();
+        let first = mock::session(1);
+        let first = WSLSessionInformation::from(&first);
+        let second = mock::session(2);
+        let second = WSLSessionInformation::from(&second);
+        let ubuntu = distribution(UBUNTU, "Ubuntu", 42);
+
+        tracker.on_vm_started(&first);
+        tracker.on_vm_started(&second);
+        tracker.on_distribution_started(&second, &ubuntu.info());
+        assert_eq!(tracker.sessions(), [1, 2]);
+        assert!(tracker.distributions(1).is_empty());
+        assert!(!tracker.on_distribution_stopping(&first, &ubuntu.info()));
+        assert!(!tracker.distribution(2, &UBUNTU).unwrap().is_stopping());
+
+        tracker.on_vm_stopping(&first);
+        assert_eq!(tracker.sessions(), [2]);
+        assert_eq!(tracker.distributions(2).len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_events() {
+        let tracker = LifecycleTracker::new();
+        let session = mock::session(3);
+        let session = WSLSessionInformation::from(&session);
+        let ubuntu = distribution(UBUNTU, "Ubuntu", 42);
+        assert!(tracker.on_vm_stopping(&session).is_empty());
+        // Loaded after the VM started
+        tracker.on_distribution_started(&session, &ubuntu.info());
+        assert!(tracker.is_live(3));
+        assert_eq!(tracker.distributions(3).len(), 1);
+    }
+
+    #[cfg(feature = "api-2-1-2")]
+    #[test]
+    fn test_registration() {
+        let tracker = LifecycleTracker::new();
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let name = encode_wide_null_terminated(OsStr::new("Debian"));
+        let offline = wslplugins_sys::WslOfflineDistributionInformation {
+            Id: DEBIAN,
+            Name: PCWSTR::from_raw(name.as_ptr()),
+            PackageFamilyName: PCWSTR::null(),
+        };
+        let offline = OfflineDistributionInformation::from(&offline);
+        tracker.on_vm_started(&session);
+        tracker.on_distribution_registered(&session, &offline);
+        assert_eq!(tracker.registered_distributions(1).len(), 1);
+        assert_eq!(tracker.registered_distributions(1)[0].name(), "Debian");
+        tracker.on_distribution_unregistered(&session, &offline);
+        assert!(tracker.registered_distributions(1).is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_queries() {
+        let tracker = Arc::new(LifecycleTracker::new());
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        tracker.on_vm_started(&session);
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let tracker = Arc::clone(&tracker);
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        let distributions = tracker.distributions(1);
+                        assert!(distributions.len() <= 1);
+                    }
+                })
+            })
+            .collect();
+        for pid in 0..100 {
+            let ubuntu = distribution(UBUNTU, "Ubuntu", pid);
+            tracker.on_distribution_started(&session, &ubuntu.info());
+            tracker.on_distribution_stopping(&session, &ubuntu.info());
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(
+            tracker.distribution(1, &UBUNTU).unwrap().info().init_pid(),
+            99
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Stand-in for the API table WSL hands to the plugin, so `ApiV1` can be unit-tested on any
+//! platform. Calls are recorded per test thread, binaries are "executed" by a peer thread
+//! holding the other end of a loopback socket.
+use crate::platform::{os_string_from_wide, socket_from_tcp_stream};
+#[cfg(feature = "api-2-1-2")]
+use crate::windows::core::GUID;
+use crate::windows::core::{HRESULT, PCSTR, PCWSTR};
+use crate::windows::Win32::Foundation::{BOOL, HANDLE, S_OK};
+use crate::windows::Win32::Networking::WinSock::SOCKET;
+use crate::windows::Win32::Security::PSID;
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use wslplugins_sys::{WSLPluginAPIV1, WSLSessionId, WSLSessionInformation, WSLVersion};
+
+type Peer = Box<dyn FnOnce(TcpStream) + Send>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Call {
+    MountFolder {
+        session: u32,
+        windows_path: OsString,
+        linux_path: OsString,
+        read_only: bool,
+        name: OsString,
+    },
+    ExecuteBinary {
+        session: u32,
+        path: String,
+        args: Vec<String>,
+    },
+    #[cfg(feature = "api-2-1-2")]
+    ExecuteBinaryInDistribution {
+        session: u32,
+        distribution: GUID,
+        path: String,
+        args: Vec<String>,
+    },
+    PluginError(OsString),
+}
+
+#[derive(Default)]
+struct State {
+    calls: Vec<Call>,
+    result: HRESULT,
+    peer: Option<Peer>,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::default();
+}
+
+/// API table of the given WSL version with every entry point implemented.
+pub(crate) fn api(major: u32, minor: u32, revision: u32) -> WSLPluginAPIV1 {
+    WSLPluginAPIV1 {
+        Version: WSLVersion {
+            Major: major,
+            Minor: minor,
+            Revision: revision,
+        },
+        MountFolder: Some(mount_folder),
+        ExecuteBinary: Some(execute_binary),
+        PluginError: Some(plugin_error),
+        #[cfg(feature = "api-2-1-2")]
+        ExecuteBinaryInDistribution: Some(execute_binary_in_distribution),
+    }
+}
+
+pub(crate) fn session(id: u32) -> WSLSessionInformation {
+    WSLSessionInformation {
+        SessionId: id,
+        UserToken: HANDLE(std::ptr::null_mut()),
+        UserSid: PSID(std::ptr::null_mut()),
+    }
+}
+
+/// Session of the user whose binary SID is `sid`, which must outlive the session.
+pub(crate) fn session_with_sid(id: u32, sid: &[u8]) -> WSLSessionInformation {
+    WSLSessionInformation {
+        UserSid: PSID(sid.as_ptr() as *mut _),
+        ..session(id)
+    }
+}
+
+/// Binary SID `S-1-5-21-<sub_authorities>`.
+pub(crate) fn sid(sub_authorities: &[u32]) -> Vec<u8> {
+    let mut sid = vec![1, 1 + sub_authorities.len() as u8, 0, 0, 0, 0, 0, 5];
+    for sub_authority in [21].iter().chain(sub_authorities) {
+        sid.extend_from_slice(&sub_authority.to_le_bytes());
+    }
+    sid
+}
+
+/// Result returned by every entry point from now on.
+pub(crate) fn set_result(result: HRESULT) {
+    STATE.with(|state| state.borrow_mut().result = result);
+}
+
+/// Runs `peer` on the Linux end of the socket of the next executed binary.
+pub(crate) fn set_peer<F: FnOnce(TcpStream) + Send + 'static>(peer: F) {
+    STATE.with(|state| state.borrow_mut().peer = Some(Box::new(peer)));
+}
+
+/// The next executed binary writes `output` then exits.
+pub(crate) fn set_output(output: &[u8]) {
+    let output = output.to_vec();
+    set_peer(move |mut stream| {
+        let _ = stream.write_all(&output);
+    });
+}
+
+pub(crate) fn calls() -> Vec<Call> {
+    STATE.with(|state| state.borrow().calls.clone())
+}
+
+fn record(call: Call) -> HRESULT {
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        state.calls.push(call);
+        state.result
+    })
+}
+
+fn spawn_peer(socket: *mut SOCKET) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (peer_stream, _) = listener.accept().unwrap();
+    let peer = STATE
+        .with(|state| state.borrow_mut().peer.take())
+        .unwrap_or_else(|| Box::new(drop));
+    thread::spawn(move || peer(peer_stream));
+    unsafe { socket.write(socket_from_tcp_stream(stream)) };
+}
+
+unsafe fn args_from_raw(arguments: *mut PCSTR) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = arguments;
+    while !(*current).is_null() {
+        args.push(String::from_utf8_lossy((*current).as_bytes()).into_owned());
+        current = current.add(1);
+    }
+    args
+}
+
+unsafe extern "C" fn mount_folder(
+    session: WSLSessionId,
+    windows_path: PCWSTR,
+    linux_path: PCWSTR,
+    read_only: BOOL,
+    name: PCWSTR,
+) -> HRESULT {
+    record(Call::MountFolder {
+        session,
+        windows_path: os_string_from_wide(windows_path.as_wide()),
+        linux_path: os_string_from_wide(linux_path.as_wide()),
+        read_only: read_only.as_bool(),
+        name: os_string_from_wide(name.as_wide()),
+    })
+}
+
+unsafe extern "C" fn execute_binary(
+    session: WSLSessionId,
+    path: PCSTR,
+    arguments: *mut PCSTR,
+    socket: *mut SOCKET,
+) -> HRESULT {
+    let result = record(Call::ExecuteBinary {
+        session,
+        path: String::from_utf8_lossy(path.as_bytes()).into_owned(),
+        args: args_from_raw(arguments),
+    });
+    if result == S_OK {
+        spawn_peer(socket);
+    }
+    result
+}
+
+#[cfg(feature = "api-2-1-2")]
+unsafe extern "C" fn execute_binary_in_distribution(
+    session: WSLSessionId,
+    distribution: *const GUID,
+    path: PCSTR,
+    arguments: *mut PCSTR,
+    socket: *mut SOCKET,
+) -> HRESULT {
+    let result = record(Call::ExecuteBinaryInDistribution {
+        session,
+        distribution: *distribution,
+        path: String::from_utf8_lossy(path.as_bytes()).into_owned(),
+        args: args_from_raw(arguments),
+    });
+    if result == S_OK {
+        spawn_peer(socket);
+    }
+    result
+}
+
+unsafe extern "C" fn plugin_error(message: PCWSTR) -> HRESULT {
+    record(Call::PluginError(os_string_from_wide(message.as_wide())))
+}
@@ -0,0 +1,252 @@
+use crate::windows::core::{Error as WinError, HRESULT};
+use crate::{ApiV1, MountSpec, WSLSessionInformation, WslError};
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::sync::Mutex;
+use typed_path::{Utf8UnixPath, Utf8UnixPathBuf};
+
+/// Mounts made through it, per session, so duplicates are rejected before reaching WSL and later
+/// hooks can tell what is mounted.
+///
+/// Shared between hooks, which may be called from several threads.
+#[derive(Debug, Default)]
+pub struct MountRegistry {
+    sessions: Mutex<HashMap<u32, Vec<MountSpec>>>,
+}
+
+impl MountRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mounts `spec` in the VM of `session` unless its target or name is already used there.
+    pub fn mount(
+        &self,
+        api: &ApiV1,
+        session: &WSLSessionInformation,
+        spec: MountSpec,
+    ) -> Result<(), MountError> {
+        // Held during the call, so concurrent mounts of the same target cannot both reach WSL
+        let mut sessions = self.sessions.lock().unwrap();
+        let mounts = sessions.entry(session.id()).or_default();
+        if mounts
+            .iter()
+            .any(|mount| mount.linux_path() == spec.linux_path())
+        {
+            return Err(MountError::DuplicateTarget(spec.linux_path().to_path_buf()));
+        }
+        if mounts.iter().any(|mount| mount.name() == spec.name()) {
+            return Err(MountError::DuplicateName(spec.name().to_owned()));
+        }
+        api.mount_folder(
+            session,
+            spec.windows_path(),
+            spec.linux_path(),
+            spec.read_only(),
+            spec.name(),
+        )
+        .map_err(MountError::Wsl)?;
+        mounts.push(spec);
+        Ok(())
+    }
+
+    /// Mounts of the session, in the order they were made.
+    pub fn mounts(&self, session_id: u32) -> Vec<MountSpec> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&session_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn find_by_target<P: AsRef<Utf8UnixPath>>(
+        &self,
+        session_id: u32,
+        linux_path: P,
+    ) -> Option<MountSpec> {
+        self.find(session_id, |mount| {
+            mount.linux_path() == linux_path.as_ref()
+        })
+    }
+
+    pub fn find_by_name<N: AsRef<OsStr>>(&self, session_id: u32, name: N) -> Option<MountSpec> {
+        self.find(session_id, |mount| mount.name() == name.as_ref())
+    }
+
+    /// Forgets the mounts of a session, to be called once its VM has stopped.
+    pub fn remove_session(&self, session_id: u32) -> Vec<MountSpec> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .remove(&session_id)
+            .unwrap_or_default()
+    }
+
+    fn find(&self, session_id: u32, predicate: impl Fn(&MountSpec) -> bool) -> Option<MountSpec> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&session_id)?
+            .iter()
+            .find(|mount| predicate(mount))
+            .cloned()
+    }
+}
+
+#[derive(Debug)]
+pub enum MountError {
+    /// Something is already mounted at this path in the session.
+    DuplicateTarget(Utf8UnixPathBuf),
+    /// A mount with this name already exists in the session.
+    DuplicateName(OsString),
+    /// WSL failed to mount the folder.
+    Wsl(WinError),
+}
+
+impl fmt::Display for MountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MountError::DuplicateTarget(path) => write!(f, "{} is already mounted", path),
+            MountError::DuplicateName(name) => {
+                write!(f, "a mount named {:?} already exists", name)
+            }
+            MountError::Wsl(err) => write!(f, "unable to mount the folder: {}", err),
+        }
+    }
+}
+
+impl Error for MountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MountError::Wsl(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<MountError> for WinError {
+    fn from(err: MountError) -> Self {
+        match err {
+            MountError::Wsl(err) => err,
+            err => WinError::new(WslError::AlreadyExists.code(), err.to_string()),
+        }
+    }
+}
+
+impl From<MountError> for HRESULT {
+    fn from(err: MountError) -> Self {
+        WinError::from(err).code()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Call};
+    use crate::windows::Win32::Foundation::E_FAIL;
+
+    fn spec(linux_path: &str, name: &str) -> MountSpec {
+        MountSpec::new("C:\\Users\\Public", linux_path, false, name).unwrap()
+    }
+
+    #[test]
+    fn test_mount() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let registry = MountRegistry::new();
+        registry
+            .mount(&api, &session, spec("/mnt/public", "public"))
+            .unwrap();
+        assert_eq!(
+            mock::calls(),
+            [Call::MountFolder {
+                session: 1,
+                windows_path: "C:\\Users\\Public".into(),
+                linux_path: "/mnt/public".into(),
+                read_only: false,
+                name: "public".into(),
+            }]
+        );
+        assert_eq!(registry.mounts(1), [spec("/mnt/public", "public")]);
+        assert_eq!(
+            registry.find_by_name(1, "public"),
+            Some(spec("/mnt/public", "public"))
+        );
+        assert_eq!(
+            registry.find_by_target(1, "/mnt/public"),
+            Some(spec("/mnt/public", "public"))
+        );
+        assert_eq!(registry.find_by_target(2, "/mnt/public"), None);
+        assert!(registry.mounts(2).is_empty());
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let registry = MountRegistry::new();
+        registry
+            .mount(&api, &session, spec("/mnt/public", "public"))
+            .unwrap();
+        let err = registry
+            .mount(&api, &session, spec("/mnt/public", "other"))
+            .unwrap_err();
+        assert!(matches!(err, MountError::DuplicateTarget(_)));
+        let err = registry
+            .mount(&api, &session, spec("/mnt/other", "public"))
+            .unwrap_err();
+        assert!(matches!(err, MountError::DuplicateName(_)));
+        assert_eq!(HRESULT::from(err), WslError::AlreadyExists.code());
+        // Rejected before reaching WSL
+        assert_eq!(mock::calls().len(), 1);
+
+        let other_session = mock::session(2);
+        registry
+            .mount(
+                &api,
+                &WSLSessionInformation::from(&other_session),
+                spec("/mnt/public", "public"),
+            )
+            .unwrap();
+        assert_eq!(mock::calls().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_mount_is_not_recorded() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let registry = MountRegistry::new();
+        mock::set_result(E_FAIL);
+        let err = registry
+            .mount(&api, &session, spec("/mnt/public", "public"))
+            .unwrap_err();
+        assert_eq!(HRESULT::from(err), E_FAIL);
+        assert!(registry.mounts(1).is_empty());
+    }
+
+    #[test]
+    fn test_remove_session() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let registry = MountRegistry::new();
+        registry
+            .mount(&api, &session, spec("/mnt/public", "public"))
+            .unwrap();
+        assert_eq!(registry.remove_session(1).len(), 1);
+        assert!(registry.mounts(1).is_empty());
+        registry
+            .mount(&api, &session, spec("/mnt/public", "public"))
+            .unwrap();
+    }
+}
@@ -0,0 +1,198 @@
+use crate::windows::core::{Error as WinError, HRESULT};
+use crate::WslError;
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use typed_path::{Utf8UnixPath, Utf8UnixPathBuf};
+
+/// Validated arguments of [`ApiV1::mount_folder`](crate::ApiV1::mount_folder).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MountSpec {
+    windows_path: PathBuf,
+    linux_path: Utf8UnixPathBuf,
+    read_only: bool,
+    name: OsString,
+}
+
+impl MountSpec {
+    /// Checks that `linux_path` is absolute, normalized and not the root, and that `name` is made
+    /// of ASCII letters, digits, `-`, `_` and `.`.
+    pub fn new<WP: AsRef<Path>, UP: AsRef<Utf8UnixPath>, N: AsRef<OsStr>>(
+        windows_path: WP,
+        linux_path: UP,
+        read_only: bool,
+        name: N,
+    ) -> Result<Self, MountSpecError> {
+        let windows_path = windows_path.as_ref();
+        let linux_path = linux_path.as_ref();
+        let name = name.as_ref();
+        if windows_path.as_os_str().is_empty() {
+            return Err(MountSpecError::EmptyWindowsPath);
+        }
+        if !linux_path.is_absolute() {
+            return Err(MountSpecError::RelativeLinuxPath(linux_path.to_path_buf()));
+        }
+        if linux_path.as_str() == "/" {
+            return Err(MountSpecError::RootLinuxPath);
+        }
+        if !is_normalized(linux_path.as_str()) {
+            return Err(MountSpecError::UnnormalizedLinuxPath(
+                linux_path.to_path_buf(),
+            ));
+        }
+        if !is_valid_name(name) {
+            return Err(MountSpecError::InvalidName(name.to_owned()));
+        }
+        Ok(Self {
+            windows_path: windows_path.to_path_buf(),
+            linux_path: linux_path.to_path_buf(),
+            read_only,
+            name: name.to_owned(),
+        })
+    }
+
+    pub fn windows_path(&self) -> &Path {
+        &self.windows_path
+    }
+
+    pub fn linux_path(&self) -> &Utf8UnixPath {
+        &self.linux_path
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn name(&self) -> &OsStr {
+        &self.name
+    }
+}
+
+// No empty, `.` or `..` component, which also rules out `//` and a trailing `/`
+fn is_normalized(path: &str) -> bool {
+    !path.contains('\0')
+        && path[1..]
+            .split('/')
+            .all(|component| !matches!(component, "" | "." | ".."))
+}
+
+fn is_valid_name(name: &OsStr) -> bool {
+    name.to_str().is_some_and(|name| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MountSpecError {
+    EmptyWindowsPath,
+    RelativeLinuxPath(Utf8UnixPathBuf),
+    /// Mounting over the root of the VM is not allowed.
+    RootLinuxPath,
+    /// The path contains an empty, `.` or `..` component.
+    UnnormalizedLinuxPath(Utf8UnixPathBuf),
+    InvalidName(OsString),
+}
+
+impl fmt::Display for MountSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MountSpecError::EmptyWindowsPath => write!(f, "the Windows path is empty"),
+            MountSpecError::RelativeLinuxPath(path) => {
+                write!(f, "the Linux path {} is not absolute", path)
+            }
+            MountSpecError::RootLinuxPath => write!(f, "cannot mount over /"),
+            MountSpecError::UnnormalizedLinuxPath(path) => {
+                write!(f, "the Linux path {} is not normalized", path)
+            }
+            MountSpecError::InvalidName(name) => {
+                write!(f, "invalid mount name {:?}", name)
+            }
+        }
+    }
+}
+
+impl Error for MountSpecError {}
+
+impl From<MountSpecError> for HRESULT {
+    fn from(_: MountSpecError) -> Self {
+        WslError::InvalidArgument.code()
+    }
+}
+
+impl From<MountSpecError> for WinError {
+    fn from(value: MountSpecError) -> Self {
+        WinError::new(HRESULT::from(value.clone()), value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(linux_path: &str, name: &str) -> Result<MountSpec, MountSpecError> {
+        MountSpec::new("C:\\Users\\Public", linux_path, true, name)
+    }
+
+    #[test]
+    fn test_valid() {
+        let spec = spec("/mnt/public", "public-1.0_a").unwrap();
+        assert_eq!(spec.windows_path(), Path::new("C:\\Users\\Public"));
+        assert_eq!(spec.linux_path(), Utf8UnixPath::new("/mnt/public"));
+        assert!(spec.read_only());
+        assert_eq!(spec.name(), "public-1.0_a");
+    }
+
+    #[test]
+    fn test_linux_path() {
+        assert!(matches!(
+            spec("mnt/public", "public"),
+            Err(MountSpecError::RelativeLinuxPath(_))
+        ));
+        assert_eq!(spec("/", "public"), Err(MountSpecError::RootLinuxPath));
+        for path in [
+            "/mnt//public",
+            "/mnt/public/",
+            "/mnt/./public",
+            "/mnt/../etc",
+        ] {
+            assert!(
+                matches!(
+                    spec(path, "public"),
+                    Err(MountSpecError::UnnormalizedLinuxPath(_))
+                ),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_name() {
+        for name in ["", "a b", "a/b", "café", "a\0"] {
+            assert_eq!(
+                spec("/mnt/public", name),
+                Err(MountSpecError::InvalidName(name.into()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_windows_path() {
+        assert_eq!(
+            MountSpec::new("", "/mnt/public", false, "public"),
+            Err(MountSpecError::EmptyWindowsPath)
+        );
+    }
+
+    #[test]
+    fn test_error_code() {
+        assert_eq!(
+            WslError::from(WinError::from(MountSpecError::RootLinuxPath)),
+            WslError::InvalidArgument
+        );
+    }
+}
@@ -1,7 +1,8 @@
 extern crate wslplugins_sys;
 use crate::core_distribution_information::CoreDistributionInformation;
-use std::{ffi::OsString, os::windows::ffi::OsStringExt};
-use windows::core::GUID;
+use crate::platform::os_string_from_wide;
+use crate::windows::core::GUID;
+use std::ffi::OsString;
 
 pub struct OfflineDistributionInformation<'a>(
     &'a wslplugins_sys::WslOfflineDistributionInformation,
@@ -19,7 +20,7 @@ impl<'a> CoreDistributionInformation for OfflineDistributionInformation<'a> {
     }
 
     fn name(&self) -> OsString {
-        unsafe { OsString::from_wide(self.0.Name.as_wide()) }
+        unsafe { os_string_from_wide(self.0.Name.as_wide()) }
     }
 
     fn package_family_name(&self) -> Option<OsString> {
@@ -28,8 +29,16 @@ impl<'a> CoreDistributionInformation for OfflineDistributionInformation<'a> {
             if ptr.is_null() || ptr.is_empty() {
                 None
             } else {
-                Some(OsString::from_wide(ptr.as_wide()))
+                Some(os_string_from_wide(ptr.as_wide()))
             }
         }
     }
 }
+
+// Same representation as the owned snapshot, so it can be read back as one
+#[cfg(feature = "serde")]
+impl serde::Serialize for OfflineDistributionInformation<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::OwnedOfflineDistributionInfo::from(self).serialize(serializer)
+    }
+}
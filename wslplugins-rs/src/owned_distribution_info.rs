@@ -0,0 +1,151 @@
+use crate::core_distribution_information::CoreDistributionInformation;
+use crate::distribution_information::DistributionInformation;
+use crate::windows::core::GUID;
+use std::ffi::OsString;
+
+/// Owned copy of a [`DistributionInformation`], which can outlive the hook call it comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedDistributionInfo {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::guid"))]
+    id: GUID,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::os_string"))]
+    name: OsString,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_utils::option_os_string")
+    )]
+    package_family_name: Option<OsString>,
+    init_pid: u32,
+    pid_namespace: u64,
+}
+
+impl OwnedDistributionInfo {
+    /// Pid of the init process. Introduced in 2.0.5
+    pub fn init_pid(&self) -> u32 {
+        self.init_pid
+    }
+
+    pub fn pid_namespace(&self) -> u64 {
+        self.pid_namespace
+    }
+}
+
+impl From<&DistributionInformation<'_>> for OwnedDistributionInfo {
+    fn from(distribution: &DistributionInformation<'_>) -> Self {
+        Self {
+            id: *distribution.id(),
+            name: distribution.name(),
+            package_family_name: distribution.package_family_name(),
+            init_pid: distribution.init_pid(),
+            pid_namespace: distribution.pid_namespace(),
+        }
+    }
+}
+
+impl CoreDistributionInformation for OwnedDistributionInfo {
+    fn id(&self) -> &GUID {
+        &self.id
+    }
+
+    fn name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    fn package_family_name(&self) -> Option<OsString> {
+        self.package_family_name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::encode_wide_null_terminated;
+    use crate::windows::core::PCWSTR;
+    use std::collections::HashMap;
+    use std::ffi::OsStr;
+    use std::thread;
+
+    const ID: GUID = GUID::from_u128(0x2b5d_0c8e_1d3a_4b5c_9e0f_1a2b_3c4d_5e6f);
+
+    fn owned(package_family_name: Option<&str>) -> OwnedDistributionInfo {
+        let name = encode_wide_null_terminated(OsStr::new("Ubuntu"));
+        let package = package_family_name.map(|name| encode_wide_null_terminated(OsStr::new(name)));
+        let native = wslplugins_sys::WSLDistributionInformation {
+            Id: ID,
+            Name: PCWSTR::from_raw(name.as_ptr()),
+            PidNamespace: 4026531836,
+            PackageFamilyName: package
+                .as_ref()
+                .map_or(PCWSTR::null(), |package| PCWSTR::from_raw(package.as_ptr())),
+            InitPid: 42,
+        };
+        OwnedDistributionInfo::from(&DistributionInformation::from(&native))
+    }
+
+    #[test]
+    fn test_from_distribution_information() {
+        let distribution = owned(Some("CanonicalGroupLimited.Ubuntu_79rhkp1fndgsc"));
+        assert_eq!(distribution.id(), &ID);
+        assert_eq!(distribution.name(), "Ubuntu");
+        assert_eq!(
+            distribution.package_family_name(),
+            Some("CanonicalGroupLimited.Ubuntu_79rhkp1fndgsc".into())
+        );
+        assert_eq!(distribution.init_pid(), 42);
+        assert_eq!(distribution.pid_namespace(), 4026531836);
+    }
+
+    #[test]
+    fn test_without_package() {
+        assert_eq!(owned(None).package_family_name(), None);
+    }
+
+    #[test]
+    fn test_send_to_thread_and_store() {
+        let distribution = owned(None);
+        let mut distributions = HashMap::new();
+        distributions.insert(*distribution.id(), distribution.clone());
+        let name = thread::spawn(move || distribution.name()).join().unwrap();
+        assert_eq!(name, "Ubuntu");
+        assert_eq!(distributions[&ID].init_pid(), 42);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let distribution = owned(Some("CanonicalGroupLimited.Ubuntu_79rhkp1fndgsc"));
+        let json = serde_json::to_string(&distribution).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"{2B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6F}","name":"Ubuntu","package_family_name":"CanonicalGroupLimited.Ubuntu_79rhkp1fndgsc","init_pid":42,"pid_namespace":4026531836}"#
+        );
+        let deserialized: OwnedDistributionInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, distribution);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_borrowed_reads_back_as_owned() {
+        let name = encode_wide_null_terminated(OsStr::new("Ubuntu"));
+        let native = wslplugins_sys::WSLDistributionInformation {
+            Id: ID,
+            Name: PCWSTR::from_raw(name.as_ptr()),
+            PidNamespace: 4026531836,
+            PackageFamilyName: PCWSTR::null(),
+            InitPid: 42,
+        };
+        let borrowed = DistributionInformation::from(&native);
+        let json = serde_json::to_string(&borrowed).unwrap();
+        let deserialized: OwnedDistributionInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, OwnedDistributionInfo::from(&borrowed));
+        assert_eq!(deserialized.package_family_name(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_invalid_guid() {
+        let json = r#"{"id":"2B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6F","name":"Ubuntu","package_family_name":null,"init_pid":42,"pid_namespace":1}"#;
+        assert!(serde_json::from_str::<OwnedDistributionInfo>(json).is_err());
+    }
+}
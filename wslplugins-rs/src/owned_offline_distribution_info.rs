@@ -0,0 +1,93 @@
+use crate::core_distribution_information::CoreDistributionInformation;
+use crate::offline_distribution_information::OfflineDistributionInformation;
+use crate::windows::core::GUID;
+use std::ffi::OsString;
+
+/// Owned copy of an [`OfflineDistributionInformation`], which can outlive the hook call it comes
+/// from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedOfflineDistributionInfo {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::guid"))]
+    id: GUID,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::os_string"))]
+    name: OsString,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serde_utils::option_os_string")
+    )]
+    package_family_name: Option<OsString>,
+}
+
+impl From<&OfflineDistributionInformation<'_>> for OwnedOfflineDistributionInfo {
+    fn from(distribution: &OfflineDistributionInformation<'_>) -> Self {
+        Self {
+            id: *distribution.id(),
+            name: distribution.name(),
+            package_family_name: distribution.package_family_name(),
+        }
+    }
+}
+
+impl CoreDistributionInformation for OwnedOfflineDistributionInfo {
+    fn id(&self) -> &GUID {
+        &self.id
+    }
+
+    fn name(&self) -> OsString {
+        self.name.clone()
+    }
+
+    fn package_family_name(&self) -> Option<OsString> {
+        self.package_family_name.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::encode_wide_null_terminated;
+    use crate::windows::core::PCWSTR;
+    use std::ffi::OsStr;
+    use std::thread;
+
+    #[test]
+    fn test_from_offline_distribution_information() {
+        let id = GUID::from_u128(0x2b5d_0c8e_1d3a_4b5c_9e0f_1a2b_3c4d_5e6f);
+        let name = encode_wide_null_terminated(OsStr::new("Debian"));
+        let package = encode_wide_null_terminated(OsStr::new(""));
+        let native = wslplugins_sys::WslOfflineDistributionInformation {
+            Id: id,
+            Name: PCWSTR::from_raw(name.as_ptr()),
+            PackageFamilyName: PCWSTR::from_raw(package.as_ptr()),
+        };
+        let distribution =
+            OwnedOfflineDistributionInfo::from(&OfflineDistributionInformation::from(&native));
+        let distribution = thread::spawn(move || distribution.clone()).join().unwrap();
+        assert_eq!(distribution.id(), &id);
+        assert_eq!(distribution.name(), "Debian");
+        assert_eq!(distribution.package_family_name(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let id = GUID::from_u128(0x2b5d_0c8e_1d3a_4b5c_9e0f_1a2b_3c4d_5e6f);
+        let name = encode_wide_null_terminated(OsStr::new("Debian"));
+        let native = wslplugins_sys::WslOfflineDistributionInformation {
+            Id: id,
+            Name: PCWSTR::from_raw(name.as_ptr()),
+            PackageFamilyName: PCWSTR::null(),
+        };
+        let json = serde_json::to_string(&OfflineDistributionInformation::from(&native)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"{2B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6F}","name":"Debian","package_family_name":null}"#
+        );
+        let deserialized: OwnedOfflineDistributionInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            deserialized,
+            OwnedOfflineDistributionInfo::from(&OfflineDistributionInformation::from(&native))
+        );
+    }
+}
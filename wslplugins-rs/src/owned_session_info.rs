@@ -0,0 +1,55 @@
+use crate::wsl_session_information::WSLSessionInformation;
+
+/// Owned copy of a [`WSLSessionInformation`], which can outlive the hook call it comes from.
+///
+/// The user token and SID are not kept: WSL only guarantees them during the call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedSessionInfo {
+    id: u32,
+}
+
+impl OwnedSessionInfo {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl From<&WSLSessionInformation<'_>> for OwnedSessionInfo {
+    fn from(session: &WSLSessionInformation<'_>) -> Self {
+        Self { id: session.id() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+    use std::thread;
+
+    #[test]
+    fn test_from_session() {
+        let session = mock::session(7);
+        let owned = OwnedSessionInfo::from(&WSLSessionInformation::from(&session));
+        assert_eq!(owned.id(), 7);
+    }
+
+    #[test]
+    fn test_send_to_thread() {
+        let session = mock::session(7);
+        let owned = OwnedSessionInfo::from(&WSLSessionInformation::from(&session));
+        let id = thread::spawn(move || owned.id()).join().unwrap();
+        assert_eq!(id, 7);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let session = mock::session(7);
+        let json = serde_json::to_string(&WSLSessionInformation::from(&session)).unwrap();
+        assert_eq!(json, r#"{"id":7}"#);
+        let owned: OwnedSessionInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(owned.id(), 7);
+        assert_eq!(serde_json::to_string(&owned).unwrap(), json);
+    }
+}
@@ -0,0 +1,107 @@
+//! Platform specific conversions.
+//!
+//! WSL only loads plugins on Windows, the other targets get stand-ins so the crate can be built
+//! and unit-tested anywhere, e.g. with a mock API handing out file descriptors as sockets.
+use crate::windows::Win32::Networking::WinSock::SOCKET;
+use std::ffi::{OsStr, OsString};
+use std::net::TcpStream;
+
+/// UTF-16 code units of `input`.
+#[cfg(windows)]
+pub(crate) fn encode_wide(input: &OsStr) -> impl Iterator<Item = u16> + '_ {
+    use std::os::windows::ffi::OsStrExt;
+    input.encode_wide()
+}
+
+/// UTF-16 code units of `input`, invalid Unicode is replaced.
+#[cfg(not(windows))]
+pub(crate) fn encode_wide(input: &OsStr) -> impl Iterator<Item = u16> + '_ {
+    input
+        .to_string_lossy()
+        .encode_utf16()
+        .collect::<Vec<u16>>()
+        .into_iter()
+}
+
+#[cfg(windows)]
+pub(crate) fn os_string_from_wide(wide: &[u16]) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+    OsString::from_wide(wide)
+}
+
+#[cfg(not(windows))]
+pub(crate) fn os_string_from_wide(wide: &[u16]) -> OsString {
+    OsString::from(String::from_utf16_lossy(wide))
+}
+
+/// Takes ownership of a socket returned by WSL.
+///
+/// # Safety
+/// `socket` must be an open socket (a file descriptor outside of Windows) not owned by anything else.
+#[cfg(windows)]
+pub(crate) unsafe fn tcp_stream_from_socket(socket: SOCKET) -> TcpStream {
+    use std::os::windows::io::{FromRawSocket, RawSocket};
+    TcpStream::from_raw_socket(socket.0 as RawSocket)
+}
+
+/// Takes ownership of a socket returned by WSL.
+///
+/// # Safety
+/// `socket` must be an open socket (a file descriptor outside of Windows) not owned by anything else.
+#[cfg(unix)]
+pub(crate) unsafe fn tcp_stream_from_socket(socket: SOCKET) -> TcpStream {
+    use std::os::unix::io::{FromRawFd, RawFd};
+    TcpStream::from_raw_fd(socket.0 as RawFd)
+}
+
+/// Releases the ownership of `stream`, as WSL does when handing out a socket.
+#[cfg(all(test, windows))]
+pub(crate) fn socket_from_tcp_stream(stream: TcpStream) -> SOCKET {
+    use std::os::windows::io::IntoRawSocket;
+    SOCKET(stream.into_raw_socket() as usize)
+}
+
+/// Releases the ownership of `stream`, as WSL does when handing out a socket.
+#[cfg(all(test, unix))]
+pub(crate) fn socket_from_tcp_stream(stream: TcpStream) -> SOCKET {
+    use std::os::unix::io::IntoRawFd;
+    SOCKET(stream.into_raw_fd() as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_encode_wide() {
+        let expected: Vec<u16> = "Hé€𝄞".encode_utf16().collect();
+        assert_eq!(
+            encode_wide(OsStr::new("Hé€𝄞")).collect::<Vec<u16>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_os_string_from_wide_round_trip() {
+        let input = OsString::from("C:\\Users\\Ünïcödé");
+        let wide: Vec<u16> = encode_wide(&input).collect();
+        assert_eq!(os_string_from_wide(&wide), input);
+    }
+
+    #[test]
+    fn test_socket_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        let socket = socket_from_tcp_stream(client);
+        let mut client = unsafe { tcp_stream_from_socket(socket) };
+        server.write_all(b"ping").unwrap();
+        drop(server);
+        let mut buf = String::new();
+        client.read_to_string(&mut buf).unwrap();
+        assert_eq!(buf, "ping");
+    }
+}
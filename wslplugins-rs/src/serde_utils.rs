@@ -0,0 +1,165 @@
+//! Serde representations shared by the wrappers.
+
+/// GUIDs in their canonical braced form, e.g. `{2B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6F}`.
+pub(crate) mod guid {
+    use crate::windows::core::GUID;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn to_braced_string(guid: &GUID) -> String {
+        format!(
+            "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+            guid.data1,
+            guid.data2,
+            guid.data3,
+            guid.data4[0],
+            guid.data4[1],
+            guid.data4[2],
+            guid.data4[3],
+            guid.data4[4],
+            guid.data4[5],
+            guid.data4[6],
+            guid.data4[7]
+        )
+    }
+
+    pub(crate) fn from_braced_str(value: &str) -> Option<GUID> {
+        let inner = value.strip_prefix('{')?.strip_suffix('}')?;
+        let groups: Vec<&str> = inner.split('-').collect();
+        let expected_lengths = [8, 4, 4, 4, 12];
+        if groups.len() != expected_lengths.len()
+            || groups
+                .iter()
+                .zip(expected_lengths)
+                .any(|(group, len)| group.len() != len)
+        {
+            return None;
+        }
+        let hex = groups.concat();
+        if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        u128::from_str_radix(&hex, 16).ok().map(GUID::from_u128)
+    }
+
+    pub(crate) fn serialize<S: Serializer>(guid: &GUID, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_braced_string(guid))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<GUID, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        from_braced_str(&value).ok_or_else(|| {
+            de::Error::invalid_value(de::Unexpected::Str(&value), &"a braced GUID string")
+        })
+    }
+}
+
+/// `OsString`s as (lossy) UTF-8 strings rather than platform specific bytes or code units.
+pub(crate) mod os_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::ffi::{OsStr, OsString};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &OsStr,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string_lossy())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OsString, D::Error> {
+        String::deserialize(deserializer).map(OsString::from)
+    }
+}
+
+/// Optional `OsString`s, see [`os_string`].
+pub(crate) mod option_os_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::ffi::{OsStr, OsString};
+
+    pub(crate) fn serialize<S: Serializer>(
+        value: &Option<impl AsRef<OsStr>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&value.as_ref().to_string_lossy()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<OsString>, D::Error> {
+        Option::<String>::deserialize(deserializer).map(|value| value.map(OsString::from))
+    }
+}
+
+/// Bytes as a base64 string, a JSON array of numbers being about four times as large.
+pub(crate) mod base64 {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(value))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        STANDARD
+            .decode(&value)
+            .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(&value), &"a base64 string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows::core::GUID;
+
+    const ID: GUID = GUID::from_u128(0x2b5d_0c8e_1d3a_4b5c_9e0f_1a2b_3c4d_5e6f);
+
+    #[test]
+    fn test_guid_braced_string() {
+        assert_eq!(
+            guid::to_braced_string(&ID),
+            "{2B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6F}"
+        );
+    }
+
+    #[test]
+    fn test_guid_round_trip() {
+        assert_eq!(
+            guid::from_braced_str(&guid::to_braced_string(&ID)),
+            Some(ID)
+        );
+        assert_eq!(
+            guid::from_braced_str("{2b5d0c8e-1d3a-4b5c-9e0f-1a2b3c4d5e6f}"),
+            Some(ID)
+        );
+    }
+
+    #[test]
+    fn test_guid_invalid() {
+        assert_eq!(
+            guid::from_braced_str("2B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6F"),
+            None
+        );
+        assert_eq!(
+            guid::from_braced_str("{2B5D0C8E1D3A-4B5C-9E0F-1A2B3C4D5E6F}"),
+            None
+        );
+        assert_eq!(
+            guid::from_braced_str("{2B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6G}"),
+            None
+        );
+        assert_eq!(
+            guid::from_braced_str("{+B5D0C8E-1D3A-4B5C-9E0F-1A2B3C4D5E6F}"),
+            None
+        );
+    }
+}
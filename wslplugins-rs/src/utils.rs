@@ -1,11 +1,10 @@
-use crate::{ApiV1, WSLPluginV1};
+use crate::{platform, ApiV1, VersionRequirementError, WSLPluginV1};
+use log::{error, warn};
 use std::ffi::{CString, OsStr};
-use std::os::windows::ffi::OsStrExt;
 use wslplugins_sys::WSLPluginAPIV1;
 
 pub fn encode_wide_null_terminated(input: &OsStr) -> Vec<u16> {
-    input
-        .encode_wide()
+    platform::encode_wide(input)
         .filter(|&c| c != 0)
         .chain(Some(0))
         .collect()
@@ -16,15 +15,41 @@ pub fn cstring_from_str(input: &str) -> CString {
     unsafe { CString::from_vec_unchecked(filtered_input) }
 }
 
+/// Creates the plugin once WSL is known to be recent enough, fails with
+/// `WSL_E_PLUGIN_REQUIRES_UPDATE` otherwise.
 pub fn create_plugin_with_required_version<'a, T: WSLPluginV1<'a>>(
     api: &'a WSLPluginAPIV1,
     required_major: u32,
     required_minor: u32,
     required_revision: u32,
-) -> windows::core::Result<T> {
-    unsafe {
-        wslplugins_sys::require_version(required_major, required_minor, required_revision, api)
-            .ok()?;
+) -> crate::windows::core::Result<T> {
+    VersionRequirementError::check(api, required_major, required_minor, required_revision)?;
+    let plugin = T::try_new(ApiV1::from(api))?;
+    Ok(plugin)
+}
+
+/// Same as [`create_plugin_with_required_version`], but an outdated WSL is also reported to the
+/// user through [`ApiV1::plugin_error`], naming the plugin.
+///
+/// The message is best effort: it is always logged, but WSL may refuse `PluginError` while the
+/// entry point runs, in which case only `WSL_E_PLUGIN_REQUIRES_UPDATE` reaches the user.
+pub fn create_named_plugin_with_required_version<'a, T: WSLPluginV1<'a>>(
+    api: &'a WSLPluginAPIV1,
+    plugin_name: &str,
+    required_major: u32,
+    required_minor: u32,
+    required_revision: u32,
+) -> crate::windows::core::Result<T> {
+    if let Err(err) =
+        VersionRequirementError::check(api, required_major, required_minor, required_revision)
+    {
+        let message = err.plugin_message(plugin_name);
+        error!("{}", message);
+        // Best effort, the version error is what WSL has to see
+        if let Err(plugin_error) = ApiV1::from(api).plugin_error(OsStr::new(&message)) {
+            warn!("WSL did not take the version message: {}", plugin_error);
+        }
+        return Err(err.into());
     }
     let plugin = T::try_new(ApiV1::from(api))?;
     Ok(plugin)
@@ -33,7 +58,19 @@ pub fn create_plugin_with_required_version<'a, T: WSLPluginV1<'a>>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{self, Call};
+    use crate::windows::core::{Result, HRESULT};
+    use crate::WslError;
     use std::ffi::OsString;
+    use wslplugins_sys::WSL_E_PLUGIN_REQUIRES_UPDATE;
+
+    struct Plugin;
+
+    impl WSLPluginV1<'_> for Plugin {
+        fn try_new(_api: ApiV1<'_>) -> Result<Self> {
+            Ok(Plugin)
+        }
+    }
 
     #[test]
     fn test_encode_wide_null_terminated_no_nulls() {
@@ -63,4 +100,51 @@ mod tests {
         let expected = "Hello".as_bytes();
         assert_eq!(cstring.into_bytes(), expected);
     }
+
+    #[test]
+    fn test_create_plugin_with_required_version() {
+        let api = mock::api(2, 1, 3);
+        assert!(create_plugin_with_required_version::<Plugin>(&api, 2, 1, 2).is_ok());
+        let api = mock::api(2, 0, 9);
+        let err = create_plugin_with_required_version::<Plugin>(&api, 2, 1, 2)
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), WSL_E_PLUGIN_REQUIRES_UPDATE);
+        assert_eq!(mock::calls(), []);
+    }
+
+    #[test]
+    fn test_create_named_plugin_with_required_version() {
+        let api = mock::api(2, 1, 3);
+        assert!(
+            create_named_plugin_with_required_version::<Plugin>(&api, "my-plugin", 2, 1, 2).is_ok()
+        );
+        assert_eq!(mock::calls(), []);
+    }
+
+    #[test]
+    fn test_create_named_plugin_with_required_version_too_old() {
+        let api = mock::api(2, 0, 9);
+        let err = create_named_plugin_with_required_version::<Plugin>(&api, "my-plugin", 2, 1, 2)
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), WSL_E_PLUGIN_REQUIRES_UPDATE);
+        assert_eq!(
+            mock::calls(),
+            [Call::PluginError(
+                "plugin my-plugin requires WSL >= 2.1.2, found 2.0.9".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_create_named_plugin_with_required_version_message_refused() {
+        let api = mock::api(2, 0, 9);
+        mock::set_result(HRESULT::from(WslError::IllegalMethodCall));
+        let err = create_named_plugin_with_required_version::<Plugin>(&api, "my-plugin", 2, 1, 2)
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), WSL_E_PLUGIN_REQUIRES_UPDATE);
+        assert_eq!(mock::calls().len(), 1);
+    }
 }
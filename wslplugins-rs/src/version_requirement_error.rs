@@ -0,0 +1,121 @@
+extern crate wslplugins_sys;
+use crate::windows::core::{Error as WinError, HRESULT};
+use crate::wsl_version::WSLVersion;
+use std::error::Error;
+use std::fmt;
+use wslplugins_sys::WSL_E_PLUGIN_REQUIRES_UPDATE;
+
+/// The running WSL is older than the version required by the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VersionRequirementError {
+    pub required: wslplugins_sys::WSLVersion,
+    pub actual: wslplugins_sys::WSLVersion,
+}
+
+impl VersionRequirementError {
+    /// Checks the version of `api` against the required one, like the `WSL_PLUGIN_REQUIRE_VERSION` macro.
+    pub fn check(
+        api: &wslplugins_sys::WSLPluginAPIV1,
+        required_major: u32,
+        required_minor: u32,
+        required_revision: u32,
+    ) -> Result<(), Self> {
+        let result = unsafe {
+            wslplugins_sys::require_version(required_major, required_minor, required_revision, api)
+        };
+        if result.is_ok() {
+            Ok(())
+        } else {
+            Err(Self {
+                required: wslplugins_sys::WSLVersion {
+                    Major: required_major,
+                    Minor: required_minor,
+                    Revision: required_revision,
+                },
+                actual: api.Version,
+            })
+        }
+    }
+
+    /// Message displayed to the user when `plugin` refuses to load.
+    pub fn plugin_message(&self, plugin: &str) -> String {
+        format!(
+            "plugin {} requires WSL >= {}, found {}",
+            plugin,
+            WSLVersion::from(&self.required),
+            WSLVersion::from(&self.actual)
+        )
+    }
+}
+
+impl fmt::Display for VersionRequirementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "WSL >= {} is required, found {}",
+            WSLVersion::from(&self.required),
+            WSLVersion::from(&self.actual)
+        )
+    }
+}
+
+impl Error for VersionRequirementError {}
+
+impl From<VersionRequirementError> for HRESULT {
+    fn from(_: VersionRequirementError) -> Self {
+        WSL_E_PLUGIN_REQUIRES_UPDATE
+    }
+}
+
+impl From<VersionRequirementError> for WinError {
+    fn from(value: VersionRequirementError) -> Self {
+        WinError::new(HRESULT::from(value), value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    #[test]
+    fn test_check_ok() {
+        let api = mock::api(2, 1, 3);
+        assert_eq!(VersionRequirementError::check(&api, 2, 1, 2), Ok(()));
+        assert_eq!(VersionRequirementError::check(&api, 2, 1, 3), Ok(()));
+    }
+
+    #[test]
+    fn test_check_too_old() {
+        let api = mock::api(2, 0, 9);
+        let err = VersionRequirementError::check(&api, 2, 1, 2).unwrap_err();
+        assert_eq!(
+            err.required,
+            wslplugins_sys::WSLVersion {
+                Major: 2,
+                Minor: 1,
+                Revision: 2
+            }
+        );
+        assert_eq!(err.actual, api.Version);
+    }
+
+    #[test]
+    fn test_messages() {
+        let api = mock::api(2, 0, 9);
+        let err = VersionRequirementError::check(&api, 2, 1, 2).unwrap_err();
+        assert_eq!(err.to_string(), "WSL >= 2.1.2 is required, found 2.0.9");
+        assert_eq!(
+            err.plugin_message("my-plugin"),
+            "plugin my-plugin requires WSL >= 2.1.2, found 2.0.9"
+        );
+    }
+
+    #[test]
+    fn test_into_hresult() {
+        let api = mock::api(2, 0, 9);
+        let err = VersionRequirementError::check(&api, 2, 1, 2).unwrap_err();
+        assert_eq!(HRESULT::from(err), WSL_E_PLUGIN_REQUIRES_UPDATE);
+        assert_eq!(WinError::from(err).code(), WSL_E_PLUGIN_REQUIRES_UPDATE);
+    }
+}
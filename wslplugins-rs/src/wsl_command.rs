@@ -0,0 +1,486 @@
+use crate::exec_options::{self, ExecError, ExecOptions};
+use crate::windows::core::Result;
+#[cfg(feature = "api-2-1-2")]
+use crate::windows::core::GUID;
+use crate::wsl_output::{self, WslOutput};
+use crate::{ApiV1, WSLSessionInformation, WslError};
+use std::net::TcpStream;
+use typed_path::{Utf8UnixPath, Utf8UnixPathBuf};
+
+const SHELL: &str = "/bin/sh";
+const ENV: &str = "/usr/bin/env";
+const SU: &str = "/bin/su";
+
+/// Builder for a program executed through [`ApiV1::execute_binary`] or
+/// [`ApiV1::execute_binary_in_distribution`], created by [`ApiV1::command`].
+///
+/// The options are turned into an argv wrapping the program with `/usr/bin/env`, `/bin/sh` or
+/// `/bin/su` as needed, see [`WslCommand::argv`].
+pub struct WslCommand<'c, 'a> {
+    api: &'c ApiV1<'a>,
+    session: &'c WSLSessionInformation<'c>,
+    program: Utf8UnixPathBuf,
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    current_dir: Option<Utf8UnixPathBuf>,
+    user: Option<String>,
+    #[cfg(feature = "api-2-1-2")]
+    distribution: Option<GUID>,
+}
+
+impl<'c, 'a> WslCommand<'c, 'a> {
+    pub(crate) fn new<P: AsRef<Utf8UnixPath>>(
+        api: &'c ApiV1<'a>,
+        session: &'c WSLSessionInformation<'c>,
+        program: P,
+    ) -> Self {
+        Self {
+            api,
+            session,
+            program: program.as_ref().to_path_buf(),
+            args: Vec::new(),
+            envs: Vec::new(),
+            current_dir: None,
+            user: None,
+            #[cfg(feature = "api-2-1-2")]
+            distribution: None,
+        }
+    }
+
+    pub fn arg<S: AsRef<str>>(&mut self, arg: S) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    pub fn args<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, args: I) -> &mut Self {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets an environment variable, the name must be a valid shell identifier.
+    pub fn env<K: AsRef<str>, V: AsRef<str>>(&mut self, key: K, value: V) -> &mut Self {
+        self.envs
+            .push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+        self
+    }
+
+    pub fn envs<I: IntoIterator<Item = (K, V)>, K: AsRef<str>, V: AsRef<str>>(
+        &mut self,
+        envs: I,
+    ) -> &mut Self {
+        for (key, value) in envs {
+            self.env(key, value);
+        }
+        self
+    }
+
+    pub fn current_dir<P: AsRef<Utf8UnixPath>>(&mut self, dir: P) -> &mut Self {
+        self.current_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Runs the program as `user` instead of root.
+    pub fn user<S: AsRef<str>>(&mut self, user: S) -> &mut Self {
+        self.user = Some(user.as_ref().to_owned());
+        self
+    }
+
+    /// Runs the program in a user distribution rather than in the root namespace.
+    /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
+    pub fn in_distribution(&mut self, distribution_id: &GUID) -> &mut Self {
+        self.distribution = Some(*distribution_id);
+        self
+    }
+
+    /// Argv actually executed, its first item is the path of the executable.
+    pub fn argv(&self) -> std::result::Result<Vec<String>, WslError> {
+        if let Some((key, _)) = self.envs.iter().find(|(key, _)| !is_valid_env_name(key)) {
+            log::error!("Invalid environment variable name {:?}", key);
+            return Err(WslError::InvalidArgument);
+        }
+        if let Some(user) = self.user.as_ref().filter(|user| !is_valid_user(user)) {
+            log::error!("Invalid user name {:?}", user);
+            return Err(WslError::InvalidArgument);
+        }
+
+        let command = self.env_command();
+        if self.current_dir.is_none() && self.user.is_none() {
+            return Ok(command);
+        }
+        // Changing the directory or the user needs a shell
+        let mut script = String::new();
+        if let Some(dir) = &self.current_dir {
+            script.push_str(&format!("cd -- {} && ", shell_quote(dir.as_str())));
+        }
+        script.push_str("exec ");
+        script.push_str(
+            &command
+                .iter()
+                .map(|arg| shell_quote(arg))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        Ok(match &self.user {
+            Some(user) => vec![
+                SU.into(),
+                "-s".into(),
+                SHELL.into(),
+                "-c".into(),
+                script,
+                user.clone(),
+            ],
+            None => vec![SHELL.into(), "-c".into(), script],
+        })
+    }
+
+    /// Starts the program, the returned stream is connected to its stdin and stdout.
+    pub fn spawn(&self) -> Result<TcpStream> {
+        self.execute(self.argv()?)
+    }
+
+    /// Runs the program to completion and collects its exit status, stdout and stderr.
+    ///
+    /// The program runs through a shell wrapper, see [`WslOutput`], its stdin is `/dev/null`.
+    pub fn output(&self) -> Result<WslOutput> {
+        let stream = self.execute(wsl_output::wrap_argv(self.argv()?))?;
+        Ok(wsl_output::read_output(stream)?)
+    }
+
+    /// Same as [`WslCommand::output`] within the limits of `options`.
+    ///
+    /// `max_output_bytes` bounds stdout and stderr together, framing included.
+    pub fn output_with_options(
+        &self,
+        options: &ExecOptions,
+    ) -> std::result::Result<WslOutput, ExecError> {
+        let stream = self.execute(wsl_output::wrap_argv(self.argv()?))?;
+        let output = exec_options::read_to_end(stream, options)?;
+        Ok(wsl_output::read_output(&output[..])?)
+    }
+
+    fn execute(&self, argv: Vec<String>) -> Result<TcpStream> {
+        let args: Vec<&str> = argv.iter().map(String::as_str).collect();
+        let path = Utf8UnixPath::new(args[0]);
+        #[cfg(feature = "api-2-1-2")]
+        if let Some(distribution) = &self.distribution {
+            return self.api.execute_binary_in_distribution(
+                self.session,
+                distribution,
+                path,
+                &args,
+            );
+        }
+        self.api.execute_binary(self.session, path, &args)
+    }
+
+    // The program and its arguments, behind `env` when variables are set
+    fn env_command(&self) -> Vec<String> {
+        let mut command = Vec::new();
+        if !self.envs.is_empty() {
+            command.push(ENV.to_owned());
+            command.extend(
+                self.envs
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value)),
+            );
+        }
+        command.push(self.program.as_str().to_owned());
+        command.extend(self.args.iter().cloned());
+        command
+    }
+}
+
+fn is_valid_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Would otherwise be taken for an option of su
+fn is_valid_user(user: &str) -> bool {
+    !user.is_empty() && !user.starts_with('-')
+}
+
+/// Quotes `arg` for a POSIX shell, single quotes keep everything literal.
+fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, Call};
+    use std::io::Read;
+
+    fn argv(build: impl FnOnce(&mut WslCommand)) -> std::result::Result<Vec<String>, WslError> {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let mut command = api.command(&session, "/usr/bin/foo");
+        build(&mut command);
+        command.argv()
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/usr/bin/foo"), "/usr/bin/foo");
+        assert_eq!(shell_quote("KEY=value"), "KEY=value");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote("$HOME; rm -rf /"), "'$HOME; rm -rf /'");
+    }
+
+    #[test]
+    fn test_program_only() {
+        assert_eq!(argv(|_| {}).unwrap(), ["/usr/bin/foo"]);
+    }
+
+    #[test]
+    fn test_args_are_passed_as_is() {
+        assert_eq!(
+            argv(|command| {
+                command
+                    .arg("--name")
+                    .arg("two words")
+                    .args(["it's", "$HOME"]);
+            })
+            .unwrap(),
+            ["/usr/bin/foo", "--name", "two words", "it's", "$HOME"]
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        assert_eq!(
+            argv(|command| {
+                command
+                    .arg("-v")
+                    .env("LANG", "C.UTF-8")
+                    .envs([("GREETING", "hello world")]);
+            })
+            .unwrap(),
+            [
+                "/usr/bin/env",
+                "LANG=C.UTF-8",
+                "GREETING=hello world",
+                "/usr/bin/foo",
+                "-v"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_current_dir() {
+        assert_eq!(
+            argv(|command| {
+                command.arg("a b").current_dir("/home/user/my dir");
+            })
+            .unwrap(),
+            [
+                "/bin/sh",
+                "-c",
+                "cd -- '/home/user/my dir' && exec /usr/bin/foo 'a b'"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_current_dir_and_env() {
+        assert_eq!(
+            argv(|command| {
+                command
+                    .env("GREETING", "it's me")
+                    .current_dir("/tmp")
+                    .arg("x");
+            })
+            .unwrap(),
+            [
+                "/bin/sh",
+                "-c",
+                "cd -- /tmp && exec /usr/bin/env 'GREETING=it'\\''s me' /usr/bin/foo x"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user() {
+        assert_eq!(
+            argv(|command| {
+                command.user("alice").arg("--flag");
+            })
+            .unwrap(),
+            [
+                "/bin/su",
+                "-s",
+                "/bin/sh",
+                "-c",
+                "exec /usr/bin/foo --flag",
+                "alice"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_name() {
+        assert_eq!(
+            argv(|command| {
+                command.env("A=B", "c");
+            }),
+            Err(WslError::InvalidArgument)
+        );
+        assert_eq!(
+            argv(|command| {
+                command.env("1A", "c");
+            }),
+            Err(WslError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_invalid_user() {
+        assert_eq!(
+            argv(|command| {
+                command.user("-c");
+            }),
+            Err(WslError::InvalidArgument)
+        );
+    }
+
+    #[test]
+    fn test_spawn() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        mock::set_output(b"hello");
+        let mut stream = api
+            .command(&session, "/bin/echo")
+            .arg("hello")
+            .env("A", "1")
+            .spawn()
+            .unwrap();
+        let mut output = String::new();
+        stream.read_to_string(&mut output).unwrap();
+        assert_eq!(output, "hello");
+        assert_eq!(
+            mock::calls(),
+            [Call::ExecuteBinary {
+                session: 1,
+                path: "/usr/bin/env".into(),
+                args: vec![
+                    "/usr/bin/env".into(),
+                    "A=1".into(),
+                    "/bin/echo".into(),
+                    "hello".into()
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_spawn_invalid() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let err = api
+            .command(&session, "/bin/echo")
+            .env("", "1")
+            .spawn()
+            .unwrap_err();
+        assert_eq!(WslError::from(err), WslError::InvalidArgument);
+        assert_eq!(mock::calls(), []);
+    }
+
+    #[cfg(feature = "api-2-1-2")]
+    #[test]
+    fn test_spawn_in_distribution() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        let distribution = GUID::from_u128(0x6f2b_1c2e_4d3a_4b5c_8d9e_0f1a_2b3c_4d5e);
+        api.command(&session, "/bin/cat")
+            .arg("/etc/os-release")
+            .current_dir("/")
+            .in_distribution(&distribution)
+            .spawn()
+            .unwrap();
+        assert_eq!(
+            mock::calls(),
+            [Call::ExecuteBinaryInDistribution {
+                session: 1,
+                distribution,
+                path: "/bin/sh".into(),
+                args: vec![
+                    "/bin/sh".into(),
+                    "-c".into(),
+                    "cd -- / && exec /bin/cat /etc/os-release".into()
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_output() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        mock::set_output(b"stdout 0\nstderr 22\ncat: missing: No such\nexit 1\n");
+        let output = api
+            .command(&session, "/bin/cat")
+            .arg("missing")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(output.stdout.is_empty());
+        assert_eq!(output.stderr, b"cat: missing: No such\n");
+        let Call::ExecuteBinary { path, args, .. } = &mock::calls()[0] else {
+            panic!("execute_binary was not called");
+        };
+        assert_eq!(path, "/bin/sh");
+        assert_eq!(args[..2], ["/bin/sh", "-c"]);
+        assert_eq!(args[3..], ["sh", "/bin/cat", "missing"]);
+    }
+
+    #[test]
+    fn test_output_truncated() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        mock::set_output(b"stdout 5\nhel");
+        assert!(api.command(&session, "/bin/echo").output().is_err());
+    }
+
+    #[test]
+    fn test_output_with_options_too_large() {
+        let api = mock::api(2, 1, 3);
+        let api = ApiV1::from(&api);
+        let session = mock::session(1);
+        let session = WSLSessionInformation::from(&session);
+        mock::set_output(b"stdout 10\n0123456789stderr 0\nexit 0\n");
+        let command = api.command(&session, "/bin/echo");
+        let err = command
+            .output_with_options(&ExecOptions::default().with_max_output_bytes(16))
+            .unwrap_err();
+        assert!(matches!(err, ExecError::OutputTooLarge(16)));
+        mock::set_output(b"stdout 10\n0123456789stderr 0\nexit 0\n");
+        let output = command
+            .output_with_options(&ExecOptions::default().with_max_output_bytes(64))
+            .unwrap();
+        assert_eq!(output.stdout, b"0123456789");
+    }
+}
@@ -0,0 +1,214 @@
+use crate::windows::core::{Error as WinError, HRESULT};
+use crate::{NotSupportedError, VersionRequirementError};
+use std::error::Error;
+use std::fmt;
+
+// Declares the known codes once, so both conversions and the messages stay in sync
+macro_rules! wsl_errors {
+    ($($(#[$attr:meta])* $variant:ident = $code:literal => $message:literal,)*) => {
+        /// Errors returned by WSL, the known codes get their own variant.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum WslError {
+            $($(#[$attr])* $variant,)*
+            /// Any other code, kept as is.
+            Other(HRESULT),
+        }
+
+        impl WslError {
+            pub fn code(&self) -> HRESULT {
+                match self {
+                    $(WslError::$variant => HRESULT($code as u32 as i32),)*
+                    WslError::Other(code) => *code,
+                }
+            }
+
+            fn message(&self) -> Option<&'static str> {
+                match self {
+                    $(WslError::$variant => Some($message),)*
+                    WslError::Other(_) => None,
+                }
+            }
+        }
+
+        impl From<HRESULT> for WslError {
+            fn from(code: HRESULT) -> Self {
+                match code.0 as u32 {
+                    $($code => WslError::$variant,)*
+                    _ => WslError::Other(code),
+                }
+            }
+        }
+    };
+}
+
+wsl_errors! {
+    /// `WSL_E_DEFAULT_DISTRO_NOT_FOUND`
+    DefaultDistributionNotFound = 0x80040300 => "no default distribution is set",
+    /// `WSL_E_DISTRO_NOT_FOUND`
+    DistributionNotFound = 0x80040301 => "the distribution was not found",
+    /// `WSL_E_WSL1_NOT_SUPPORTED`
+    Wsl1NotSupported = 0x80040302 => "the operation is not supported by WSL 1 distributions",
+    /// `WSL_E_VM_MODE_NOT_SUPPORTED`
+    VmModeNotSupported = 0x80040303 => "the operation is not supported by WSL 2 distributions",
+    /// `WSL_E_TOO_MANY_DISKS_ATTACHED`
+    TooManyDisksAttached = 0x80040304 => "too many disks are attached to the VM",
+    /// `WSL_E_VM_MODE_INVALID_STATE`, the VM is not running
+    VmNotRunning = 0x80040308 => "the VM is not running",
+    /// `WSL_E_PLUGIN_REQUIRES_UPDATE`
+    PluginRequiresUpdate = 0x8004032A => "the plugin requires a newer version of WSL",
+    /// `E_ILLEGAL_METHOD_CALL`
+    IllegalMethodCall = 0x8000000E => "a method was called at an unexpected time",
+    /// `E_NOTIMPL`
+    NotImplemented = 0x80004001 => "not implemented",
+    /// `E_POINTER`
+    InvalidPointer = 0x80004003 => "invalid pointer",
+    /// `E_ABORT`
+    Aborted = 0x80004004 => "operation aborted",
+    /// `E_FAIL`
+    Failed = 0x80004005 => "unspecified failure",
+    /// `E_UNEXPECTED`
+    Unexpected = 0x8000FFFF => "unexpected failure",
+    /// `HRESULT_FROM_WIN32(ERROR_FILE_NOT_FOUND)`
+    FileNotFound = 0x80070002 => "the file was not found",
+    /// `HRESULT_FROM_WIN32(ERROR_PATH_NOT_FOUND)`
+    PathNotFound = 0x80070003 => "the path was not found",
+    /// `E_ACCESSDENIED`
+    AccessDenied = 0x80070005 => "access denied",
+    /// `E_OUTOFMEMORY`
+    OutOfMemory = 0x8007000E => "out of memory",
+    /// `HRESULT_FROM_WIN32(ERROR_NOT_SUPPORTED)`
+    NotSupported = 0x80070032 => "not supported",
+    /// `E_INVALIDARG`
+    InvalidArgument = 0x80070057 => "invalid argument",
+    /// `HRESULT_FROM_WIN32(ERROR_BUSY)`
+    Busy = 0x800700AA => "the resource is busy",
+    /// `HRESULT_FROM_WIN32(ERROR_ALREADY_EXISTS)`
+    AlreadyExists = 0x800700B7 => "already exists",
+    /// `HRESULT_FROM_WIN32(ERROR_TIMEOUT)`
+    Timeout = 0x800705B4 => "the operation timed out",
+    /// Not a WSL code: the entry point is missing from the API table, see [`NotSupportedError`].
+    /// The customer bit is set so it cannot be mistaken for a failure reported by WSL itself.
+    EntryPointNotSupported = 0xA0040001 => "the entry point is not supported by this version of WSL",
+}
+
+impl fmt::Display for WslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(message) => write!(f, "{} ({:#010X})", message, self.code().0),
+            None => write!(f, "WSL error {:#010X}", self.code().0),
+        }
+    }
+}
+
+impl Error for WslError {}
+
+impl From<WslError> for HRESULT {
+    fn from(error: WslError) -> Self {
+        error.code()
+    }
+}
+
+impl From<WinError> for WslError {
+    fn from(error: WinError) -> Self {
+        WslError::from(error.code())
+    }
+}
+
+impl From<NotSupportedError> for WslError {
+    fn from(_: NotSupportedError) -> Self {
+        WslError::EntryPointNotSupported
+    }
+}
+
+impl From<VersionRequirementError> for WslError {
+    fn from(error: VersionRequirementError) -> Self {
+        WslError::from(HRESULT::from(error))
+    }
+}
+
+impl From<WslError> for WinError {
+    fn from(error: WslError) -> Self {
+        WinError::from(error.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows::Win32::Foundation::{E_FAIL, E_POINTER};
+    use crate::{ApiEntryPoint, NotSupportedError};
+    use wslplugins_sys::WSL_E_PLUGIN_REQUIRES_UPDATE;
+
+    #[test]
+    fn test_from_hresult() {
+        assert_eq!(
+            WslError::from(WSL_E_PLUGIN_REQUIRES_UPDATE),
+            WslError::PluginRequiresUpdate
+        );
+        assert_eq!(WslError::from(E_FAIL), WslError::Failed);
+        assert_eq!(WslError::from(E_POINTER), WslError::InvalidPointer);
+        assert_eq!(
+            WslError::from(HRESULT(0x80040301_u32 as i32)),
+            WslError::DistributionNotFound
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for error in [
+            WslError::DistributionNotFound,
+            WslError::VmNotRunning,
+            WslError::PathNotFound,
+            WslError::Other(HRESULT(0x80041234_u32 as i32)),
+        ] {
+            assert_eq!(WslError::from(HRESULT::from(error)), error);
+            assert_eq!(WslError::from(WinError::from(error)), error);
+        }
+    }
+
+    #[test]
+    fn test_unknown_code_is_kept() {
+        let code = HRESULT(0x80041234_u32 as i32);
+        let error = WslError::from(code);
+        assert_eq!(error, WslError::Other(code));
+        assert_eq!(error.code(), code);
+        assert_eq!(error.to_string(), "WSL error 0x80041234");
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            WslError::DistributionNotFound.to_string(),
+            "the distribution was not found (0x80040301)"
+        );
+    }
+
+    #[test]
+    fn test_from_not_supported_error() {
+        let error = NotSupportedError {
+            entry_point: ApiEntryPoint::ExecuteBinary,
+            version: wslplugins_sys::WSLVersion {
+                Major: 2,
+                Minor: 0,
+                Revision: 9,
+            },
+        };
+        assert_eq!(WslError::from(error), WslError::EntryPointNotSupported);
+        assert_eq!(
+            WslError::from(WinError::from(error)),
+            WslError::EntryPointNotSupported
+        );
+        assert_ne!(
+            WslError::from(WinError::from(error)),
+            WslError::NotSupported
+        );
+    }
+
+    #[test]
+    fn test_from_version_requirement_error() {
+        let api = crate::mock::api(2, 0, 9);
+        let error = VersionRequirementError::check(&api, 2, 1, 2).unwrap_err();
+        assert_eq!(WslError::from(error), WslError::PluginRequiresUpdate);
+    }
+}
@@ -0,0 +1,233 @@
+//! Output of a program run through [`WslCommand::output`](crate::WslCommand::output).
+//!
+//! WSL hands out a single socket connected to the stdin and stdout of the program, so the
+//! program is run by a small shell wrapper which buffers its stdout and stderr, then writes them
+//! followed by the exit code as frames:
+//!
+//! ```text
+//! stdout <length>\n<bytes>stderr <length>\n<bytes>exit <code>\n
+//! ```
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+
+pub(crate) const WRAPPER_SHELL: &str = "/bin/sh";
+
+// Run as `sh -c WRAPPER_SCRIPT sh <argv...>`
+// The program runs in the background while a watcher reads the socket: the plugin never writes
+// to it, so EOF means the plugin gave up (see `ExecOptions`) and the program is killed. The signal
+// traps exit so that the temporary files are also removed when the wrapper is killed, or gets
+// SIGPIPE once the plugin side is closed
+pub(crate) const WRAPPER_SCRIPT: &str = r#"out=$(mktemp) && err=$(mktemp) || exit 125
+trap 'rm -f "$out" "$err"' EXIT
+trap 'kill $child $watcher 2>/dev/null; exit 1' HUP INT TERM PIPE
+exec 3<&0
+"$@" </dev/null >"$out" 2>"$err" 3<&- &
+child=$!
+{ while read -r _; do :; done; kill $child 2>/dev/null; } <&3 &
+watcher=$!
+exec 3<&-
+wait $child
+status=$?
+kill $watcher 2>/dev/null
+printf 'stdout %s\n' "$(wc -c <"$out")" && cat "$out"
+printf 'stderr %s\n' "$(wc -c <"$err")" && cat "$err"
+printf 'exit %s\n' "$status""#;
+
+/// Exit status of a program executed in WSL, as reported by the shell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WslExitStatus(i32);
+
+impl WslExitStatus {
+    pub fn success(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Exit code, `128 + n` when the program was killed by signal `n`.
+    pub fn code(&self) -> i32 {
+        self.0
+    }
+}
+
+impl fmt::Display for WslExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "exit status: {}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WslOutput {
+    pub status: WslExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Argv running `argv` through the wrapper.
+pub(crate) fn wrap_argv(argv: Vec<String>) -> Vec<String> {
+    [WRAPPER_SHELL, "-c", WRAPPER_SCRIPT, "sh"]
+        .into_iter()
+        .map(String::from)
+        .chain(argv)
+        .collect()
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_header<R: BufRead>(reader: &mut R, expected_tag: &str) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("missing {} frame", expected_tag),
+        ));
+    }
+    let line = line
+        .strip_suffix('\n')
+        .ok_or_else(|| invalid_data(format!("truncated {} header", expected_tag)))?;
+    match line.split_once(' ') {
+        Some((tag, value)) if tag == expected_tag => Ok(value.trim().to_owned()),
+        _ => Err(invalid_data(format!(
+            "expected a {} frame, found {:?}",
+            expected_tag, line
+        ))),
+    }
+}
+
+fn read_stream<R: BufRead>(reader: &mut R, tag: &str) -> io::Result<Vec<u8>> {
+    let len: u64 = read_header(reader, tag)?
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid {} length", tag)))?;
+    let mut data = Vec::new();
+    reader.take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("truncated {} frame", tag),
+        ));
+    }
+    Ok(data)
+}
+
+/// Parses the frames written by the wrapper.
+pub(crate) fn read_output<R: Read>(reader: R) -> io::Result<WslOutput> {
+    let mut reader = BufReader::new(reader);
+    let stdout = read_stream(&mut reader, "stdout")?;
+    let stderr = read_stream(&mut reader, "stderr")?;
+    let status = read_header(&mut reader, "exit")?
+        .parse()
+        .map_err(|_| invalid_data("invalid exit code".into()))?;
+    Ok(WslOutput {
+        status: WslExitStatus(status),
+        stdout,
+        stderr,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_output() {
+        let output = read_output(&b"stdout 6\nhello\nstderr 0\nexit 0\n"[..]).unwrap();
+        assert_eq!(
+            output,
+            WslOutput {
+                status: WslExitStatus(0),
+                stdout: b"hello\n".to_vec(),
+                stderr: Vec::new(),
+            }
+        );
+        assert!(output.status.success());
+    }
+
+    #[test]
+    fn test_read_output_binary_and_failure() {
+        let output =
+            read_output(&b"stdout 3\n\x00\n\xffstderr   13\nno such file\nexit 1\n"[..]).unwrap();
+        assert_eq!(output.stdout, b"\x00\n\xff");
+        assert_eq!(output.stderr, b"no such file\n");
+        assert!(!output.status.success());
+        assert_eq!(output.status.code(), 1);
+    }
+
+    #[test]
+    fn test_read_output_truncated() {
+        let err = read_output(&b"stdout 10\nhello"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read_output(&b"stdout 0\nstderr 0\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = read_output(&b""[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_read_output_invalid() {
+        let err = read_output(&b"stderr 0\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = read_output(&b"stdout x\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = read_output(&b"stdout 0\nstderr 0\nexit -\n"[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // Runs the wrapper with its stdin left open, as the plugin side does
+    #[cfg(unix)]
+    fn run_wrapper(argv: Vec<String>) -> Vec<u8> {
+        use std::process::{Command, Stdio};
+        let argv = wrap_argv(argv);
+        let mut wrapper = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut output = Vec::new();
+        wrapper
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_end(&mut output)
+            .unwrap();
+        wrapper.wait().unwrap();
+        output
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wrapper_script() {
+        let output = run_wrapper(vec![
+            "/bin/sh".into(),
+            "-c".into(),
+            "sleep 0.1; printf 'out'; printf 'err' >&2; exit 3".into(),
+        ]);
+        let output = read_output(&output[..]).unwrap();
+        assert_eq!(output.stdout, b"out");
+        assert_eq!(output.stderr, b"err");
+        assert_eq!(output.status.code(), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wrapper_removes_files_on_sigpipe() {
+        use std::process::Command;
+        let tmp = std::env::temp_dir().join(format!("wslplugins-wrapper-{}", std::process::id()));
+        std::fs::create_dir(&tmp).unwrap();
+        // Nobody reads the output anymore, as when the plugin side has given up
+        let (reader, writer) = io::pipe().unwrap();
+        drop(reader);
+        let argv = wrap_argv(vec!["/bin/echo".into(), "hello".into()]);
+        let mut wrapper = Command::new(&argv[0])
+            .args(&argv[1..])
+            .env("TMPDIR", &tmp)
+            .stdin(std::process::Stdio::piped())
+            .stdout(writer)
+            .spawn()
+            .unwrap();
+        assert!(!wrapper.wait().unwrap().success());
+        let left = std::fs::read_dir(&tmp).unwrap().count();
+        std::fs::remove_dir_all(&tmp).unwrap();
+        assert_eq!(left, 0);
+    }
+}
@@ -1,11 +1,12 @@
+#[cfg(feature = "api-2-1-2")]
+use crate::offline_distribution_information::OfflineDistributionInformation;
+use crate::windows::core::Result;
 use crate::{
     distribution_information::DistributionInformation,
-    offline_distribution_information::OfflineDistributionInformation,
     wsl_session_information::WSLSessionInformation,
     wsl_vm_creation_settings::WSLVmCreationSettings, ApiV1,
 };
 use std::marker::Sized;
-use windows::core::Result;
 
 /// Trait defining synchronous notifications sent to the plugin.
 pub trait WSLPluginV1<'a>: Sized + Sync {
@@ -54,6 +55,7 @@ pub trait WSLPluginV1<'a>: Sized + Sync {
     ///
     /// Returning failure will NOT cause the operation to fail.
     /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
     #[allow(unused_variables)]
     fn on_distribution_registered(
         &self,
@@ -66,6 +68,7 @@ pub trait WSLPluginV1<'a>: Sized + Sync {
     ///
     /// Returning failure will NOT cause the operation to fail.
     /// Introduced in 2.1.2
+    #[cfg(feature = "api-2-1-2")]
     #[allow(unused_variables)]
     fn on_distribution_unregistered(
         &self,
@@ -1,8 +1,8 @@
 extern crate wslplugins_sys;
+use crate::windows::Win32::Foundation::*;
+use crate::windows::Win32::Security::PSID;
 use core::hash;
 use std::fmt;
-use windows::Win32::Foundation::*;
-use windows::Win32::Security::PSID;
 
 pub struct WSLSessionInformation<'a>(&'a wslplugins_sys::WSLSessionInformation);
 
@@ -33,6 +33,36 @@ impl WSLSessionInformation<'_> {
     pub fn user_sid(&self) -> PSID {
         self.0.UserSid
     }
+
+    /// String form of the user SID, e.g. `S-1-5-21-1004336348-1177238915-682003330-1001`,
+    /// `None` when WSL did not provide one.
+    pub fn user_sid_string(&self) -> Option<String> {
+        // WSL guarantees the SID for the duration of the hook call
+        unsafe { sid_to_string(self.0.UserSid) }
+    }
+}
+
+const SID_MAX_SUB_AUTHORITIES: usize = 15;
+
+// Binary layout of a SID: revision, sub-authority count, 48-bit big-endian identifier authority,
+// then the little-endian sub-authorities
+unsafe fn sid_to_string(sid: PSID) -> Option<String> {
+    let sid = sid.0 as *const u8;
+    if sid.is_null() {
+        return None;
+    }
+    let revision = *sid;
+    let count = *sid.add(1) as usize;
+    if count > SID_MAX_SUB_AUTHORITIES {
+        return None;
+    }
+    let authority = (0..6).fold(0u64, |acc, i| (acc << 8) | *sid.add(2 + i) as u64);
+    let mut string = format!("S-{}-{}", revision, authority);
+    for i in 0..count {
+        let sub_authority = sid.add(8 + 4 * i).cast::<[u8; 4]>().read_unaligned();
+        string.push_str(&format!("-{}", u32::from_le_bytes(sub_authority)));
+    }
+    Some(string)
 }
 
 impl<'a> From<&'a wslplugins_sys::WSLSessionInformation> for WSLSessionInformation<'a> {
@@ -45,9 +75,40 @@ impl<'a> From<&'a wslplugins_sys::WSLSessionInformation> for WSLSessionInformati
 impl fmt::Debug for WSLSessionInformation<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("WSLSessionInformation")
-            .field("SessionId", &(*self.0).SessionId)
-            .field("UserToken", &(*self.0).UserToken)
-            .field("UserSid", &(*self.0).UserSid)
+            .field("SessionId", &self.0.SessionId)
+            .field("UserToken", &self.0.UserToken)
+            .field("UserSid", &self.0.UserSid)
             .finish()
     }
 }
+
+// Same representation as the owned snapshot, so it can be read back as one
+#[cfg(feature = "serde")]
+impl serde::Serialize for WSLSessionInformation<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::OwnedSessionInfo::from(self).serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    #[test]
+    fn test_user_sid_string() {
+        let sid = mock::sid(&[1004336348, 1177238915, 682003330, 1001]);
+        let session = mock::session_with_sid(1, &sid);
+        assert_eq!(
+            WSLSessionInformation::from(&session)
+                .user_sid_string()
+                .as_deref(),
+            Some("S-1-5-21-1004336348-1177238915-682003330-1001")
+        );
+        let session = mock::session(1);
+        assert_eq!(
+            WSLSessionInformation::from(&session).user_sid_string(),
+            None
+        );
+    }
+}
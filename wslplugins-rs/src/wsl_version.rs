@@ -98,6 +98,18 @@ impl fmt::Display for WSLVersionError {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for WSLVersion<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("WSLVersion", 3)?;
+        state.serialize_field("major", &self.major())?;
+        state.serialize_field("minor", &self.minor())?;
+        state.serialize_field("revision", &self.revision())?;
+        state.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -208,4 +220,18 @@ mod tests {
             "Build metadata field is not empty"
         );
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize() {
+        let native_version = NativeWSLVersion {
+            Major: 2,
+            Minor: 1,
+            Revision: 3,
+        };
+        assert_eq!(
+            serde_json::to_string(&WSLVersion::from(&native_version)).unwrap(),
+            r#"{"major":2,"minor":1,"revision":3}"#
+        );
+    }
 }
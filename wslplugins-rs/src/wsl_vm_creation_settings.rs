@@ -1,5 +1,4 @@
 use crate::wsl_user_configuration::WSLUserConfiguration;
-use wslplugins_sys;
 
 pub struct WSLVmCreationSettings<'a>(&'a wslplugins_sys::WSLVmCreationSettings);
 
@@ -10,8 +9,6 @@ impl<'a> From<&'a wslplugins_sys::WSLVmCreationSettings> for WSLVmCreationSettin
 }
 
 impl WSLVmCreationSettings<'_> {
-    
-
     #[cfg(feature = "bitflags")]
     pub fn custom_configuration_flags(&self) -> WSLUserConfiguration {
         WSLUserConfiguration::from_bits_truncate(self.0.CustomConfigurationFlags)
@@ -19,11 +16,48 @@ impl WSLVmCreationSettings<'_> {
 
     #[cfg(feature = "flagset")]
     pub fn custom_configuration_flags(&self) -> WSLUserConfiguration {
-            WSLUserConfiguration::from_bits_retain(self.0.CustomConfigurationFlags).unwrap()
+        WSLUserConfiguration::from_bits_retain(self.0.CustomConfigurationFlags).unwrap()
     }
 
     #[cfg(feature = "enumflags2")]
     pub fn custom_configuration_flags(&self) -> WSLUserConfiguration {
-            WSLUserConfiguration::from_bits_truncate(self.0.CustomConfigurationFlags as u8)
+        WSLUserConfiguration::from_bits_truncate(self.0.CustomConfigurationFlags as u8)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for WSLVmCreationSettings<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("WSLVmCreationSettings", 1)?;
+        state.serialize_field(
+            "custom_configuration_flags",
+            &self.custom_configuration_flags(),
+        )?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(feature = "serde", feature = "bitflags"))]
+    #[test]
+    fn test_serde_flags_round_trip() {
+        use super::*;
+        let native = wslplugins_sys::WSLVmCreationSettings {
+            CustomConfigurationFlags: (WSLUserConfiguration::CustomKernel
+                | WSLUserConfiguration::CustomKernelCommandLine)
+                .bits(),
+        };
+        let settings = WSLVmCreationSettings::from(&native);
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            json,
+            r#"{"custom_configuration_flags":"CustomKernel | CustomKernelCommandLine"}"#
+        );
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let flags: WSLUserConfiguration =
+            serde_json::from_value(value["custom_configuration_flags"].clone()).unwrap();
+        assert_eq!(flags, settings.custom_configuration_flags());
     }
 }
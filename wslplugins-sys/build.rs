@@ -1,36 +1,61 @@
+#[cfg(feature = "generate-bindings")]
 extern crate bindgen;
-extern crate semver;
 
+#[cfg(feature = "generate-bindings")]
 use bindgen::callbacks::{ParseCallbacks, TypeKind};
-use semver::Version;
+#[cfg(feature = "generate-bindings")]
+use sha2::{Digest, Sha256};
 use std::env;
-use std::path::{Path, PathBuf};
-use std::process::{Command, ExitStatus};
+use std::fs;
+use std::path::PathBuf;
+#[cfg(feature = "generate-bindings")]
+use std::{
+    fmt,
+    io::{self, Cursor},
+    path::Path,
+};
+#[cfg(feature = "generate-bindings")]
+use zip::{result::ZipError, ZipArchive};
 
-const WSL_PACKAGE_NAME: &str = "Microsoft.WSL.PluginApi";
-const LOCAL_NUGET_PATH: &str = "nuget_packages"; // Local folder to store NuGet packages
+#[cfg(not(feature = "api-2-0-5"))]
+compile_error!(
+    "No WSL Plugin API version selected, enable one of the `api-*` features of wslplugins-sys."
+);
 
-#[derive(Debug)]
-struct BindgenCallback {
-    generate_hooks_fields_name: bool,
-}
+#[cfg(not(any(feature = "generate-bindings", feature = "vendored-bindings")))]
+compile_error!(
+    "Either the `vendored-bindings` or the `generate-bindings` feature of wslplugins-sys must be enabled."
+);
 
-impl Default for BindgenCallback {
-    fn default() -> Self {
-        Self {
-            generate_hooks_fields_name: false,
-        }
-    }
-}
+#[cfg(feature = "generate-bindings")]
+const WSL_PACKAGE_NAME: &str = "Microsoft.WSL.PluginApi";
+#[cfg(feature = "generate-bindings")]
+const LOCAL_NUGET_PATH: &str = "nuget_packages"; // Local folder to store NuGet packages
+#[cfg(feature = "generate-bindings")]
+const NUPKG_ENV: &str = "WSLPLUGINS_NUPKG"; // Explicit path to the .nupkg
+#[cfg(feature = "generate-bindings")]
+const NUPKG_HASHES_FILE: &str = "nupkg.sha256"; // Pinned hashes, one package per line
+#[cfg(feature = "generate-bindings")]
+const NUPKG_HEADER_ENTRY: &str = "build/native/include/WslPluginApi.h";
+const VENDORED_BINDINGS_DIR: &str = "vendored"; // Checked-in bindings, one file per header version
+const HOOKS_STRUCT_DECLARATION: &str = "pub struct WSLPluginHooksV1 {";
+// Structs shared with WSL, their layout is asserted at compile time by the bindings
+const ABI_STRUCTS: &[&str] = &[
+    "WSLVersion",
+    "WSLVmCreationSettings",
+    "WSLSessionInformation",
+    "WSLDistributionInformation",
+    "WSLPluginHooksV1",
+    "WSLPluginAPIV1",
+];
+// Only declared from the 2.1.2 header
+const ABI_STRUCTS_2_1_2: &[&str] = &["WslOfflineDistributionInformation"];
 
-impl BindgenCallback {
-    fn new(generate_hooks_fields_names: bool) -> Self {
-        BindgenCallback {
-            generate_hooks_fields_name: generate_hooks_fields_names,
-        }
-    }
-}
+#[cfg(feature = "generate-bindings")]
+#[derive(Debug, Default)]
+struct BindgenCallback;
 
+#[cfg(feature = "generate-bindings")]
 impl ParseCallbacks for BindgenCallback {
     fn add_derives(&self, _info: &bindgen::callbacks::DeriveInfo<'_>) -> Vec<String> {
         if _info.kind == TypeKind::Struct && _info.name == "WSLVersion" {
@@ -41,85 +66,184 @@ impl ParseCallbacks for BindgenCallback {
                 "PartialOrd".into(),
                 "Hash".into(),
             ]
-        } else if _info.kind == TypeKind::Struct
-            && _info.name.contains("PluginHooks")
-            && self.generate_hooks_fields_name
-        {
-            vec!["FieldNamesAsSlice".into()]
         } else {
             vec![]
         }
     }
 }
 
-// Function to ensure the NuGet package is installed in the local folder
-fn ensure_package_installed(
-    package_name: &str,
-    package_version: &str,
-    output_dir: &str,
-) -> Result<ExitStatus, Box<dyn std::error::Error>> {
-    // Run the NuGet install command with -NonInteractive to avoid prompts
-    let status = Command::new("nuget")
-        .args(&[
-            "install",
-            package_name,
-            "-Version",
-            package_version,
-            "-OutputDirectory",
-            output_dir,        // Local folder to install the NuGet package
-            "-NonInteractive", // Ensures the command runs without user interaction
-        ])
-        .status()
-        .expect("Failed to execute nuget install command");
-
-    if !status.success() {
-        return Err(format!(
-            "NuGet install command failed with status: {:?}",
-            status.code()
-        )
-        .into());
-    }
-    Ok(status)
+#[cfg(feature = "generate-bindings")]
+enum NupkgError {
+    NotFound(Vec<PathBuf>),
+    NoPinnedHash {
+        path: PathBuf,
+        version: String,
+        actual: String,
+    },
+    HashMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    HeaderMissing(PathBuf),
+    Io(PathBuf, std::io::Error),
+    Zip(PathBuf, zip::result::ZipError),
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Extract the version of the package from the Cargo metadata
-    let version_str = env!("CARGO_PKG_VERSION");
-    let version = Version::parse(version_str).expect("Unable to parse the Cargo package version");
-    let build_metadata = &version.build;
+#[cfg(feature = "generate-bindings")]
+impl fmt::Display for NupkgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NupkgError::NotFound(searched) => {
+                write!(f, "{} package not found, searched:", WSL_PACKAGE_NAME)?;
+                for path in searched {
+                    write!(f, " {:?}", path)?;
+                }
+                write!(f, " (set {} to the .nupkg path)", NUPKG_ENV)
+            }
+            NupkgError::NoPinnedHash {
+                path,
+                version,
+                actual,
+            } => write!(
+                f,
+                "No SHA-256 pinned for {} {} in {}; {:?} hashes to {}, run `wslplugins-sys/pin-nupkg.sh {}` to download the package, check it against nuget.org and pin it",
+                WSL_PACKAGE_NAME, version, NUPKG_HASHES_FILE, path, actual, version
+            ),
+            NupkgError::HashMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "SHA-256 mismatch for {:?}: expected {}, found {}",
+                path, expected, actual
+            ),
+            NupkgError::HeaderMissing(path) => {
+                write!(f, "{} not found in {:?}", NUPKG_HEADER_ENTRY, path)
+            }
+            NupkgError::Io(path, err) => write!(f, "Unable to read {:?}: {}", path, err),
+            NupkgError::Zip(path, err) => write!(f, "Invalid package {:?}: {}", path, err),
+        }
+    }
+}
 
-    println!("cargo:rerun-if-changed=build.rs");
-    println!("cargo:version={}", version);
-    if !build_metadata.is_empty() {
-        println!("cargo:build-metadata={}", build_metadata);
+// Build scripts report a failing `main` with `Debug`, keep the readable message
+#[cfg(feature = "generate-bindings")]
+impl fmt::Debug for NupkgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
     }
+}
 
-    let package_version = build_metadata.to_string();
+#[cfg(feature = "generate-bindings")]
+impl std::error::Error for NupkgError {}
 
-    // Ensure the NuGet package is installed in the specified local directory
-    ensure_package_installed(WSL_PACKAGE_NAME, &package_version, LOCAL_NUGET_PATH)?;
+// Locate the .nupkg either from the environment or from the local package cache
+#[cfg(feature = "generate-bindings")]
+fn find_package(package_version: &str) -> Result<PathBuf, NupkgError> {
+    if let Some(path) = env::var_os(NUPKG_ENV) {
+        let path = PathBuf::from(path);
+        return if path.is_file() {
+            Ok(path)
+        } else {
+            Err(NupkgError::NotFound(vec![path]))
+        };
+    }
+    // NuGet lowercases ids in its caches but keeps them as published in downloads
+    let candidates: Vec<PathBuf> = [
+        format!("{}.{}.nupkg", WSL_PACKAGE_NAME, package_version),
+        format!(
+            "{}.{}.nupkg",
+            WSL_PACKAGE_NAME.to_lowercase(),
+            package_version
+        ),
+    ]
+    .into_iter()
+    .map(|file_name| Path::new(LOCAL_NUGET_PATH).join(file_name))
+    .collect();
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .ok_or(NupkgError::NotFound(candidates))
+}
 
-    // Construct the full path to the installed package in the local directory
-    let package_path =
-        Path::new(LOCAL_NUGET_PATH).join(format!("{:}.{:}", WSL_PACKAGE_NAME, package_version));
+// Expected SHA-256 of the package, from the pinned hashes file only
+#[cfg(feature = "generate-bindings")]
+fn pinned_sha256(package_version: &str) -> Result<Option<String>, NupkgError> {
+    let pinned = fs::read_to_string(NUPKG_HASHES_FILE)
+        .map_err(|err| NupkgError::Io(PathBuf::from(NUPKG_HASHES_FILE), err))?;
+    let file_name = format!("{}.{}.nupkg", WSL_PACKAGE_NAME, package_version).to_lowercase();
+    // Same format as the output of `sha256sum`
+    Ok(pinned
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*').to_lowercase() == file_name)
+        .map(|(hash, _)| hash.to_lowercase()))
+}
 
-    // Construct the path to the header file
-    let header_file_path = package_path
-        .join("build")
-        .join("native")
-        .join("include")
-        .join("WslPluginApi.h");
+// Check the package hash and extract WslPluginApi.h into `output_dir`
+#[cfg(feature = "generate-bindings")]
+fn extract_header(
+    package_version: &str,
+    output_dir: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let package_path = find_package(package_version)?;
+    println!("cargo:rerun-if-changed={}", package_path.display());
+    let package =
+        fs::read(&package_path).map_err(|err| NupkgError::Io(package_path.clone(), err))?;
 
-    // Check if the header file exists
-    if !header_file_path.exists() {
-        return Err(format!("Header file does not exist: {:?}", header_file_path).into());
+    let actual: String = Sha256::digest(&package)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let Some(expected) = pinned_sha256(package_version)? else {
+        return Err(NupkgError::NoPinnedHash {
+            path: package_path,
+            version: package_version.to_string(),
+            actual,
+        }
+        .into());
+    };
+    if actual != expected {
+        return Err(NupkgError::HashMismatch {
+            path: package_path,
+            expected,
+            actual,
+        }
+        .into());
     }
 
+    let mut archive = ZipArchive::new(Cursor::new(package))
+        .map_err(|err| NupkgError::Zip(package_path.clone(), err))?;
+    let mut header = match archive.by_name(NUPKG_HEADER_ENTRY) {
+        Ok(header) => header,
+        Err(ZipError::FileNotFound) => return Err(NupkgError::HeaderMissing(package_path).into()),
+        Err(err) => return Err(NupkgError::Zip(package_path, err).into()),
+    };
+    let header_file_path = output_dir.join("WslPluginApi.h");
+    let mut header_file = fs::File::create(&header_file_path)
+        .map_err(|err| NupkgError::Io(header_file_path.clone(), err))?;
+    io::copy(&mut header, &mut header_file)
+        .map_err(|err| NupkgError::Io(header_file_path.clone(), err))?;
+    Ok(header_file_path)
+}
+
+// Run bindgen against the WslPluginApi.h header of the given package version
+#[cfg(feature = "generate-bindings")]
+fn generate_bindings(
+    package_version: &str,
+    out_dir: &Path,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let header_file_path = extract_header(package_version, out_dir)?;
+
     println!("Using header file from: {:?}", header_file_path);
 
     // Use bindgen to generate Rust bindings from the header file
-    let hooks_fields_name_feature = env::var("CARGO_FEATURE_HOOKS_FIELD_NAMES").is_ok();
-    let mut builder = bindgen::Builder::default()
+    let wslplugins_sys = bindgen::Builder::default()
         .header(header_file_path.to_str().unwrap())
         .raw_line("use windows::core::*;")
         .raw_line("use windows::Win32::Foundation::*;")
@@ -127,30 +251,119 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .raw_line("use windows::Win32::Networking::WinSock::SOCKET;")
         .raw_line("type LPCWSTR = PCWSTR;")
         .raw_line("type LPCSTR = PCSTR;")
-        .raw_line("type DWORD = u32;");
-
-    if hooks_fields_name_feature {
-        builder = builder.raw_line("use struct_field_names_as_array::FieldNamesAsSlice;");
-    }
-
-    let wslplugins_sys = builder
+        .raw_line("type DWORD = u32;")
         .derive_debug(true)
         .derive_copy(true)
+        .layout_tests(true)
         .allowlist_item("WSL.*")
         .allowlist_item("Wsl.*")
         .clang_arg("-fparse-all-comments")
         .allowlist_recursively(false)
-        .parse_callbacks(Box::new(BindgenCallback::new(hooks_fields_name_feature)))
+        .parse_callbacks(Box::new(BindgenCallback))
         .generate_comments(true)
         .generate()
         .expect("Unable to generate wslplugins_sys");
 
-    // Write the generated bindings to the OUT_DIR
+    Ok(wslplugins_sys.to_string())
+}
+
+// Bindgen emits `const` size, alignment and offset assertions next to each struct, so a
+// binding that does not match the header fails to compile. Make sure none of them went missing.
+fn check_layout_assertions(bindings: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let structs = ABI_STRUCTS.iter().chain(if cfg!(feature = "api-2-1-2") {
+        ABI_STRUCTS_2_1_2
+    } else {
+        &[]
+    });
+    let missing: Vec<&str> = structs
+        .filter(|name| !bindings.contains(&format!("[\"Size of {}\"]", name)))
+        .copied()
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The bindings have no layout assertions for {}, regenerate them with layout tests enabled",
+            missing.join(", ")
+        )
+        .into())
+    }
+}
+
+// Derive `FieldNamesAsSlice` on the hooks struct, the same way for vendored and generated bindings
+fn add_hooks_field_names(bindings: &str) -> Result<String, Box<dyn std::error::Error>> {
+    if !bindings.contains(HOOKS_STRUCT_DECLARATION) {
+        return Err("WSLPluginHooksV1 not found in the bindings".into());
+    }
+    let bindings = bindings.replacen(
+        HOOKS_STRUCT_DECLARATION,
+        &format!("#[derive(FieldNamesAsSlice)]\n{}", HOOKS_STRUCT_DECLARATION),
+        1,
+    );
+    Ok(format!(
+        "use struct_field_names_as_array::FieldNamesAsSlice;\n{}",
+        bindings
+    ))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The `api-*` features are cumulative, the newest enabled header version wins
+    let package_version = if cfg!(feature = "api-2-1-3") {
+        "2.1.3"
+    } else if cfg!(feature = "api-2-1-2") {
+        "2.1.2"
+    } else {
+        "2.0.5"
+    };
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:api-version={}", package_version);
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let vendored_bindings_path = PathBuf::from(env::var("CARGO_MANIFEST_DIR")?)
+        .join(VENDORED_BINDINGS_DIR)
+        .join(format!("WslPluginApi-{}.rs", package_version));
+    println!(
+        "cargo:rustc-env=WSLPLUGINS_SYS_VENDORED_BINDINGS={}",
+        vendored_bindings_path.display()
+    );
+
+    #[cfg(feature = "generate-bindings")]
+    let bindings = {
+        println!("cargo:rerun-if-env-changed={}", NUPKG_ENV);
+        println!("cargo:rerun-if-changed={}", NUPKG_HASHES_FILE);
+        let generated = generate_bindings(package_version, &out_dir)?;
+        // Keep the raw output around so it can be compared with (or copied over) the vendored file
+        fs::write(out_dir.join("generated_bindings.rs"), &generated)?;
+        generated
+    };
+
+    #[cfg(all(not(feature = "generate-bindings"), feature = "vendored-bindings"))]
+    let bindings = {
+        println!(
+            "cargo:rerun-if-changed={}",
+            vendored_bindings_path.display()
+        );
+        fs::read_to_string(&vendored_bindings_path).map_err(|err| {
+            format!(
+                "No vendored bindings for WSL Plugin API {} ({}): {}",
+                package_version,
+                vendored_bindings_path.display(),
+                err
+            )
+        })?
+    };
+
+    check_layout_assertions(&bindings)?;
+
+    let bindings = if env::var("CARGO_FEATURE_HOOKS_FIELD_NAMES").is_ok() {
+        add_hooks_field_names(&bindings)?
+    } else {
+        bindings
+    };
+
+    // Write the bindings to the OUT_DIR
     let wslplugins_sys_out_path = out_dir.join("wslplugins_sys.rs");
-    wslplugins_sys
-        .write_to_file(wslplugins_sys_out_path)
-        .expect("Couldn't write wslplugins_sys!");
+    fs::write(wslplugins_sys_out_path, bindings).expect("Couldn't write wslplugins_sys!");
 
     Ok(())
 }
@@ -0,0 +1,15 @@
+#!/bin/sh
+# Regenerates the bindings of each header version with bindgen and checks that they match the
+# vendored ones, from the packages pinned by pin-nupkg.sh. Needs libclang.
+# Usage: ./check-bindings.sh [version...], 2.0.5 2.1.2 and 2.1.3 by default
+set -eu
+
+cd "$(dirname "$0")"
+
+[ $# -gt 0 ] || set -- 2.0.5 2.1.2 2.1.3
+for version in "$@"; do
+    feature=api-$(printf '%s' "$version" | tr . -)
+    echo "Checking vendored/WslPluginApi-$version.rs"
+    cargo test --no-default-features --features "generate-bindings,$feature" \
+        test_vendored_bindings_match_generated
+done
@@ -0,0 +1,40 @@
+#!/bin/sh
+# Downloads the Microsoft.WSL.PluginApi packages into nuget_packages/, checks each one against the
+# SHA-512 published in the nuget.org catalog, then pins its SHA-256 in nupkg.sha256.
+# Usage: ./pin-nupkg.sh [version...], 2.0.5 2.1.2 and 2.1.3 by default
+# Needs curl, jq, openssl and sha256sum. NUGET replaces https://api.nuget.org, e.g. for a mirror.
+set -eu
+
+nuget=${NUGET:-https://api.nuget.org}
+id=microsoft.wsl.pluginapi
+cd "$(dirname "$0")"
+mkdir -p nuget_packages
+
+[ $# -gt 0 ] || set -- 2.0.5 2.1.2 2.1.3
+for version in "$@"; do
+    file=Microsoft.WSL.PluginApi.$version.nupkg
+    package=nuget_packages/$file
+    curl -fsSL -o "$package" "$nuget/v3-flatcontainer/$id/$version/$id.$version.nupkg"
+
+    catalog=$(curl -fsSL "$nuget/v3/registration5-semver1/$id/$version.json" | jq -r .catalogEntry)
+    entry=$(curl -fsSL "$catalog")
+    algorithm=$(printf '%s' "$entry" | jq -r .packageHashAlgorithm)
+    published=$(printf '%s' "$entry" | jq -r .packageHash)
+    if [ "$algorithm" != SHA512 ]; then
+        echo "$file: unexpected hash algorithm $algorithm in $catalog" >&2
+        exit 1
+    fi
+    actual=$(openssl dgst -sha512 -binary "$package" | base64 | tr -d '\n')
+    if [ "$actual" != "$published" ]; then
+        echo "$file does not match the SHA-512 published in $catalog" >&2
+        rm -f "$package"
+        exit 1
+    fi
+
+    line=$(cd nuget_packages && sha256sum "$file")
+    # Replaces the line of the package, if any
+    awk -v file="$file" 'tolower($2) != tolower(file)' nupkg.sha256 >nupkg.sha256.tmp
+    printf '%s\n' "$line" >>nupkg.sha256.tmp
+    mv nupkg.sha256.tmp nupkg.sha256
+    echo "$line"
+done
@@ -1,4 +1,22 @@
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
+#![allow(clippy::upper_case_acronyms)]
+#[cfg(not(windows))]
+use crate::windows;
 include!(concat!(env!("OUT_DIR"), "/wslplugins_sys.rs"));
+
+#[cfg(all(test, feature = "generate-bindings"))]
+mod vendored_tests {
+    #[test]
+    fn test_vendored_bindings_match_generated() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/generated_bindings.rs"));
+        let vendored = include_str!(env!("WSLPLUGINS_SYS_VENDORED_BINDINGS"));
+        assert!(
+            generated == vendored,
+            "{} is out of date, replace it with {}",
+            env!("WSLPLUGINS_SYS_VENDORED_BINDINGS"),
+            concat!(env!("OUT_DIR"), "/generated_bindings.rs")
+        );
+    }
+}
@@ -1,4 +1,8 @@
+#[cfg(windows)]
+pub extern crate windows;
 mod bindgen;
 mod manual;
+#[cfg(not(windows))]
+pub mod windows;
 pub use crate::bindgen::*;
 pub use manual::*;
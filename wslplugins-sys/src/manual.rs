@@ -1,3 +1,5 @@
+#[cfg(not(windows))]
+use crate::windows;
 use crate::WSLPluginAPIV1;
 use windows::core::HRESULT;
 use windows::Win32::Foundation::{SEVERITY_ERROR, S_OK};
@@ -8,9 +10,14 @@ const fn make_hresult(severity: u32, facility: FACILITY_CODE, code: u32) -> HRES
     HRESULT(((severity << 31) | (facility.0 << 16) | code) as i32)
 }
 
-const WSL_E_PLUGIN_REQUIRES_UPDATE: HRESULT =
+/// Returned by [`require_version`] when WSL is older than the plugin requires.
+pub const WSL_E_PLUGIN_REQUIRES_UPDATE: HRESULT =
     make_hresult(SEVERITY_ERROR, FACILITY_ITF, 0x8004032A);
 
+/// Checks the WSL version against the required one, like the `WSL_PLUGIN_REQUIRE_VERSION` macro.
+///
+/// # Safety
+/// `api` must point to a valid `WSLPluginAPIV1`.
 #[inline(always)]
 pub const unsafe fn require_version(
     required_major: u32,
@@ -49,6 +56,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(feature = "api-2-1-2")]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -66,6 +74,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(feature = "api-2-1-2")]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -86,6 +95,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(feature = "api-2-1-2")]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -106,6 +116,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(feature = "api-2-1-2")]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -126,6 +137,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(feature = "api-2-1-2")]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -0,0 +1,147 @@
+//! Stand-in for the subset of the `windows` crate used by the bindings.
+//!
+//! The `windows` crate is empty outside of Windows, this module provides the same types with the
+//! same layout so the bindings and everything built on them can be compiled and unit-tested on
+//! other targets. `GUID`, `HRESULT` and `Error` are the real ones from `windows-core`.
+#![allow(non_snake_case, non_camel_case_types, clippy::upper_case_acronyms)]
+
+pub mod core {
+    pub use windows_core::{Error, Result, GUID, HRESULT};
+
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct PCWSTR(pub *const u16);
+
+    impl PCWSTR {
+        pub const fn from_raw(ptr: *const u16) -> Self {
+            Self(ptr)
+        }
+
+        pub const fn null() -> Self {
+            Self(std::ptr::null())
+        }
+
+        pub const fn as_ptr(&self) -> *const u16 {
+            self.0
+        }
+
+        pub fn is_null(&self) -> bool {
+            self.0.is_null()
+        }
+
+        /// # Safety
+        /// The pointer must be null terminated.
+        pub unsafe fn len(&self) -> usize {
+            let mut len = 0;
+            while *self.0.add(len) != 0 {
+                len += 1;
+            }
+            len
+        }
+
+        /// # Safety
+        /// The pointer must be null terminated.
+        pub unsafe fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        /// # Safety
+        /// The pointer must be null terminated and stay valid for the returned lifetime.
+        pub unsafe fn as_wide(&self) -> &[u16] {
+            std::slice::from_raw_parts(self.0, self.len())
+        }
+    }
+
+    #[repr(transparent)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct PCSTR(pub *const u8);
+
+    impl PCSTR {
+        pub const fn from_raw(ptr: *const u8) -> Self {
+            Self(ptr)
+        }
+
+        pub const fn null() -> Self {
+            Self(std::ptr::null())
+        }
+
+        pub const fn as_ptr(&self) -> *const u8 {
+            self.0
+        }
+
+        pub fn is_null(&self) -> bool {
+            self.0.is_null()
+        }
+
+        /// # Safety
+        /// The pointer must be null terminated and stay valid for the returned lifetime.
+        pub unsafe fn as_bytes(&self) -> &[u8] {
+            std::ffi::CStr::from_ptr(self.0 as *const std::ffi::c_char).to_bytes()
+        }
+    }
+}
+
+pub mod Win32 {
+    pub mod Foundation {
+        use crate::windows::core::HRESULT;
+
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        pub struct BOOL(pub i32);
+
+        impl BOOL {
+            pub fn as_bool(self) -> bool {
+                self.0 != 0
+            }
+        }
+
+        impl From<bool> for BOOL {
+            fn from(value: bool) -> Self {
+                Self(value.into())
+            }
+        }
+
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct HANDLE(pub *mut std::ffi::c_void);
+
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        pub struct WIN32_ERROR(pub u32);
+
+        pub const SEVERITY_ERROR: u32 = 1u32;
+        pub const S_OK: HRESULT = HRESULT(0x0_u32 as _);
+        pub const E_ABORT: HRESULT = HRESULT(0x80004004_u32 as _);
+        pub const E_FAIL: HRESULT = HRESULT(0x80004005_u32 as _);
+        pub const E_POINTER: HRESULT = HRESULT(0x80004003_u32 as _);
+        pub const E_UNEXPECTED: HRESULT = HRESULT(0x8000FFFF_u32 as _);
+        pub const ERROR_NOT_SUPPORTED: WIN32_ERROR = WIN32_ERROR(50u32);
+    }
+
+    pub mod Security {
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct PSID(pub *mut std::ffi::c_void);
+    }
+
+    pub mod Networking {
+        pub mod WinSock {
+            /// Carries a file descriptor outside of Windows.
+            #[repr(transparent)]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct SOCKET(pub usize);
+        }
+    }
+
+    pub mod System {
+        pub mod Diagnostics {
+            pub mod Debug {
+                #[repr(transparent)]
+                #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+                pub struct FACILITY_CODE(pub u32);
+
+                pub const FACILITY_ITF: FACILITY_CODE = FACILITY_CODE(4u32);
+            }
+        }
+    }
+}